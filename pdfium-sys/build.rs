@@ -14,7 +14,9 @@ fn main() {
         .opaque_type("std::.*")
         .whitelist_type("FPDF.*")
         .whitelist_function("FPDF.*")
+        .whitelist_function("FORM_.*")
         .whitelist_var("FPDF.*")
+        .whitelist_var("PDFACTION_.*")
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
         .generate()
         .unwrap()
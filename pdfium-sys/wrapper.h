@@ -3,4 +3,10 @@
 #include <fpdf_save.h>
 #include <fpdf_ppo.h>
 #include <fpdf_annot.h>
-#include <fpdf_flatten.h>
\ No newline at end of file
+#include <fpdf_attachment.h>
+#include <fpdf_flatten.h>
+#include <fpdf_formfill.h>
+#include <fpdf_progressive.h>
+#include <fpdf_text.h>
+#include <fpdf_doc.h>
+#include <fpdf_catalog.h>
\ No newline at end of file
@@ -1,5 +1,6 @@
+use crate::syntax::concat;
 use anyhow::anyhow;
-use image::{DynamicImage, EncodableLayout, GenericImageView};
+use image::{DynamicImage, EncodableLayout, GenericImageView, GrayImage, RgbImage, RgbaImage};
 use libjpegturbo_sys as j;
 use pdfium_sys as p;
 use std::ffi::c_void;
@@ -8,6 +9,7 @@ use std::io::Write;
 use std::ops::Deref;
 use std::os::raw::{c_int, c_ulong};
 use std::path::Path;
+use std::time::Instant;
 use std::{fmt, mem, ptr, slice};
 use utf16string::{LittleEndian, WString};
 
@@ -74,6 +76,145 @@ impl Document {
         unsafe { p::FPDF_GetPageCount(self.ptr) as usize }
     }
 
+    // Returns eg. 14 for PDF 1.4, or None for documents created from scratch.
+    pub fn file_version(&self) -> Option<i32> {
+        let mut version = 0;
+        let ok = unsafe { p::FPDF_GetFileVersion(self.ptr, &mut version) };
+        if ok == 1 {
+            Some(version)
+        } else {
+            None
+        }
+    }
+
+    // Returns the value of an Info dictionary entry such as "Title" or "ModDate", or None if
+    // it isn't set.
+    pub fn meta_text(&self, tag: &str) -> Option<String> {
+        let ctag = CString::new(tag).unwrap();
+        // Returns the length in bytes of the UTF-16LE value, including a null terminator.
+        let len = unsafe { p::FPDF_GetMetaText(self.ptr, ctag.as_ptr(), ptr::null_mut(), 0) };
+        if len <= 2 {
+            return None;
+        }
+        let mut buf: Vec<u16> = vec![0; len as usize / 2];
+        unsafe {
+            p::FPDF_GetMetaText(
+                self.ptr,
+                ctag.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                len,
+            )
+        };
+        buf.pop();
+        Some(String::from_utf16_lossy(&buf))
+    }
+
+    // Returns the label of the page at `pos`, eg. "iv", or None if the page isn't labelled.
+    pub fn page_label(&self, pos: usize) -> Option<String> {
+        // Returns the length in bytes of the UTF-16LE label, including a null terminator.
+        let len = unsafe { p::FPDF_GetPageLabel(self.ptr, pos as c_int, ptr::null_mut(), 0) };
+        if len <= 2 {
+            return None;
+        }
+        let mut buf: Vec<u16> = vec![0; len as usize / 2];
+        unsafe {
+            p::FPDF_GetPageLabel(self.ptr, pos as c_int, buf.as_mut_ptr() as *mut c_void, len)
+        };
+        buf.pop();
+        Some(String::from_utf16_lossy(&buf))
+    }
+
+    // Returns the permission flags from the encryption dictionary - see table 3.20 of the PDF
+    // reference. All bits are set for unencrypted documents.
+    pub fn permissions(&self) -> u32 {
+        unsafe { p::FPDF_GetDocPermissions(self.ptr) as u32 }
+    }
+
+    // Returns None for unencrypted documents.
+    pub fn security_handler_revision(&self) -> Option<i32> {
+        let revision = unsafe { p::FPDF_GetSecurityHandlerRevision(self.ptr) };
+        if revision < 0 {
+            None
+        } else {
+            Some(revision)
+        }
+    }
+
+    // Returns the permanent or the changing half of the document's ID, if it has one.
+    pub fn file_identifier(&self, permanent: bool) -> Option<Vec<u8>> {
+        let id_type = if permanent {
+            p::FPDF_FILEIDTYPE_FILEIDTYPE_PERMANENT
+        } else {
+            p::FPDF_FILEIDTYPE_FILEIDTYPE_CHANGING
+        };
+        // Returns the length in bytes, including a null terminator.
+        let len = unsafe { p::FPDF_GetFileIdentifier(self.ptr, id_type, ptr::null_mut(), 0) };
+        if len <= 1 {
+            return None;
+        }
+        let mut buf: Vec<u8> = vec![0; len as usize];
+        unsafe {
+            p::FPDF_GetFileIdentifier(self.ptr, id_type, buf.as_mut_ptr() as *mut c_void, len)
+        };
+        buf.pop();
+        Some(buf)
+    }
+
+    pub fn is_tagged(&self) -> bool {
+        unsafe { p::FPDFCatalog_IsTagged(self.ptr) == 1 }
+    }
+
+    // Returns the first child of `parent`, or the first top-level bookmark if `parent` is None.
+    pub fn first_bookmark(&self, parent: Option<&Bookmark>) -> Option<Bookmark> {
+        let parent_ptr = parent.map_or(ptr::null_mut(), |bookmark| bookmark.ptr);
+        let ptr = unsafe { p::FPDFBookmark_GetFirstChild(self.ptr, parent_ptr) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Bookmark { ptr })
+        }
+    }
+
+    pub fn next_bookmark(&self, bookmark: &Bookmark) -> Option<Bookmark> {
+        let ptr = unsafe { p::FPDFBookmark_GetNextSibling(self.ptr, bookmark.ptr) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Bookmark { ptr })
+        }
+    }
+
+    pub fn attachment_count(&self) -> usize {
+        unsafe { p::FPDFDoc_GetAttachmentCount(self.ptr).max(0) as usize }
+    }
+
+    pub fn load_attachment(&self, pos: usize) -> Result<Attachment> {
+        let ptr = unsafe { p::FPDFDoc_GetAttachment(self.ptr, pos as c_int) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(Attachment { ptr })
+    }
+
+    // Fails if there's already an attachment called `name`.
+    pub fn add_attachment(&self, name: &str) -> Result<Attachment> {
+        let w_name = to_wide_string(name);
+        let ptr =
+            unsafe { p::FPDFDoc_AddAttachment(self.ptr, w_name.as_ptr() as p::FPDF_WIDESTRING) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(Attachment { ptr })
+    }
+
+    // Only removes the attachment from the document's list of attachments - its data is left
+    // in the file.
+    pub fn delete_attachment(&self, pos: usize) -> Result<()> {
+        handle_fail(unsafe { p::FPDFDoc_DeleteAttachment(self.ptr, pos as c_int) })
+    }
+
     pub fn create_page(&self, pos: usize, width: f64, height: f64) -> Result<Page> {
         let ptr = unsafe { p::FPDFPage_New(self.ptr, pos as i32, width, height) };
         handle_err()?;
@@ -106,6 +247,18 @@ impl Document {
         })
     }
 
+    pub fn init_form_fill_environment(&self) -> Result<FormFillEnvironment> {
+        // PDFium holds on to the info struct so it needs a stable address.
+        let mut info: Box<p::FPDF_FORMFILLINFO> = Box::new(unsafe { mem::zeroed() });
+        info.version = 1;
+        let ptr = unsafe { p::FPDFDOC_InitFormFillEnvironment(self.ptr, info.as_mut()) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(FormFillEnvironment { ptr, _info: info })
+    }
+
     pub fn load_standard_font(&self, font: &str) -> Result<Font> {
         let c_font = CString::new(font).unwrap();
         let ptr = unsafe { p::FPDFText_LoadStandardFont(self.ptr, c_font.as_ptr()) };
@@ -219,6 +372,179 @@ impl Drop for Document {
     }
 }
 
+// Attachments belong to the document and aren't closed.
+pub struct Attachment {
+    ptr: p::FPDF_ATTACHMENT,
+}
+
+impl Attachment {
+    pub fn name(&self) -> String {
+        // Returns the length in bytes of the UTF-16LE name, including a null terminator.
+        let len = unsafe { p::FPDFAttachment_GetName(self.ptr, ptr::null_mut(), 0) };
+        if len <= 2 {
+            return String::new();
+        }
+        let mut buf: Vec<u16> = vec![0; len as usize / 2];
+        unsafe { p::FPDFAttachment_GetName(self.ptr, buf.as_mut_ptr(), len) };
+        buf.pop();
+        String::from_utf16_lossy(&buf)
+    }
+
+    // Returns a value from the embedded file's parameters, eg. "ModDate", or None if it isn't
+    // set. PDFium returns the "CheckSum" MD5 digest hex encoded.
+    pub fn string_value(&self, key: &str) -> Option<String> {
+        let ckey = CString::new(key).unwrap();
+        if unsafe { p::FPDFAttachment_HasKey(self.ptr, ckey.as_ptr()) } != 1 {
+            return None;
+        }
+        let len = unsafe {
+            p::FPDFAttachment_GetStringValue(self.ptr, ckey.as_ptr(), ptr::null_mut(), 0)
+        };
+        if len <= 2 {
+            return None;
+        }
+        let mut buf: Vec<u16> = vec![0; len as usize / 2];
+        unsafe { p::FPDFAttachment_GetStringValue(self.ptr, ckey.as_ptr(), buf.as_mut_ptr(), len) };
+        buf.pop();
+        Some(String::from_utf16_lossy(&buf))
+    }
+
+    // Fails for attachments which refer to a file rather than embedding it.
+    pub fn file(&self) -> Result<Vec<u8>> {
+        let mut len = 0;
+        handle_fail(unsafe { p::FPDFAttachment_GetFile(self.ptr, ptr::null_mut(), 0, &mut len) })?;
+        let mut buf: Vec<u8> = vec![0; len as usize];
+        handle_fail(unsafe {
+            p::FPDFAttachment_GetFile(
+                self.ptr,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as c_ulong,
+                &mut len,
+            )
+        })?;
+        buf.truncate(len as usize);
+        Ok(buf)
+    }
+
+    // Also sets the size, creation date and checksum parameters.
+    pub fn set_file(&self, doc: &Document, contents: &[u8]) -> Result<()> {
+        handle_fail(unsafe {
+            p::FPDFAttachment_SetFile(
+                self.ptr,
+                doc.ptr,
+                contents.as_ptr() as *const c_void,
+                contents.len() as c_ulong,
+            )
+        })
+    }
+}
+
+// Bookmarks belong to the document and aren't closed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bookmark {
+    ptr: p::FPDF_BOOKMARK,
+}
+
+impl Bookmark {
+    pub fn title(&self) -> String {
+        // Returns the length in bytes of the UTF-16LE title, including a null terminator.
+        let len = unsafe { p::FPDFBookmark_GetTitle(self.ptr, ptr::null_mut(), 0) };
+        if len <= 2 {
+            return String::new();
+        }
+        let mut buf: Vec<u16> = vec![0; len as usize / 2];
+        unsafe { p::FPDFBookmark_GetTitle(self.ptr, buf.as_mut_ptr() as *mut c_void, len) };
+        buf.pop();
+        String::from_utf16_lossy(&buf)
+    }
+
+    // Returns where the bookmark points, either directly or through a GoTo action, or None
+    // if it points outside of the document or nowhere at all.
+    pub fn destination(&self, doc: &Document) -> Option<Destination> {
+        let mut dest = unsafe { p::FPDFBookmark_GetDest(doc.ptr, self.ptr) };
+        if dest.is_null() {
+            let action = unsafe { p::FPDFBookmark_GetAction(self.ptr) };
+            if action.is_null()
+                || unsafe { p::FPDFAction_GetType(action) } != p::PDFACTION_GOTO as c_ulong
+            {
+                return None;
+            }
+            dest = unsafe { p::FPDFAction_GetDest(doc.ptr, action) };
+            if dest.is_null() {
+                return None;
+            }
+        }
+
+        let page = unsafe { p::FPDFDest_GetDestPageIndex(doc.ptr, dest) };
+        if page < 0 {
+            return None;
+        }
+        let (mut has_x, mut has_y, mut has_zoom) = (0, 0, 0);
+        let (mut x, mut y, mut zoom) = (0., 0., 0.);
+        let ok = unsafe {
+            p::FPDFDest_GetLocationInPage(
+                dest,
+                &mut has_x,
+                &mut has_y,
+                &mut has_zoom,
+                &mut x,
+                &mut y,
+                &mut zoom,
+            )
+        };
+        let ok = ok == 1;
+        Some(Destination {
+            page: page as usize,
+            x: Some(x).filter(|_| ok && has_x == 1),
+            y: Some(y).filter(|_| ok && has_y == 1),
+            zoom: Some(zoom).filter(|_| ok && has_zoom == 1),
+        })
+    }
+}
+
+pub struct Destination {
+    // Zero-based.
+    pub page: usize,
+    // In points from the bottom left of the page. Missing coordinates and zoom are left as
+    // they were by viewers.
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub zoom: Option<f32>,
+}
+
+// Must be dropped before the document it was created for.
+pub struct FormFillEnvironment {
+    ptr: p::FPDF_FORMHANDLE,
+    _info: Box<p::FPDF_FORMFILLINFO>,
+}
+
+impl FormFillEnvironment {
+    // Draws form fields (and their values) on top of a page previously rendered into `bmp`.
+    pub fn draw(
+        &self,
+        bmp: &Bitmap,
+        page: &Page,
+        width: i32,
+        height: i32,
+        rotation: i32,
+        flags: i32,
+    ) {
+        unsafe {
+            p::FORM_OnAfterLoadPage(page.ptr, self.ptr);
+            p::FPDF_FFLDraw(
+                self.ptr, bmp.ptr, page.ptr, 0, 0, width, height, rotation, flags,
+            );
+            p::FORM_OnBeforeClosePage(page.ptr, self.ptr);
+        }
+    }
+}
+
+impl Drop for FormFillEnvironment {
+    fn drop(&mut self) {
+        unsafe { p::FPDFDOC_ExitFormFillEnvironment(self.ptr) };
+    }
+}
+
 pub struct Page {
     ptr: p::FPDF_PAGE,
 }
@@ -235,6 +561,38 @@ impl Page {
         Ok(Object { ptr })
     }
 
+    // Returns every object on the page in drawing order, including those inside form XObjects.
+    pub fn nested_objects(&self) -> Vec<NestedObject> {
+        // A stack of the objects left to visit, in reverse order.
+        let mut stack: Vec<NestedObject> = (0..self.object_count())
+            .rev()
+            .filter_map(|pos| {
+                Some(NestedObject {
+                    object: self.load_object(pos).ok()?,
+                    pos: Some(pos),
+                    matrix: [1., 0., 0., 1., 0., 0.],
+                })
+            })
+            .collect();
+        let mut objects = vec![];
+        while let Some(nested) = stack.pop() {
+            let form_objects = nested.object.form_objects();
+            if !form_objects.is_empty() {
+                if let Ok(form_matrix) = nested.object.matrix() {
+                    // The objects inside a form are positioned by its matrix.
+                    let matrix = concat(form_matrix, nested.matrix);
+                    stack.extend(form_objects.into_iter().rev().map(|object| NestedObject {
+                        object,
+                        pos: None,
+                        matrix,
+                    }));
+                }
+            }
+            objects.push(nested);
+        }
+        objects
+    }
+
     pub fn width(&self) -> f32 {
         unsafe { p::FPDF_GetPageWidthF(self.ptr) }
     }
@@ -243,6 +601,19 @@ impl Page {
         unsafe { p::FPDF_GetPageHeightF(self.ptr) }
     }
 
+    // Returns the (left, bottom, right, top) of the displayed area of the page - its crop box
+    // clipped to its media box - which needn't start at (0, 0). Rotation is ignored.
+    pub fn bounding_box(&self) -> Result<(f32, f32, f32, f32)> {
+        let mut rect = p::FS_RECTF {
+            left: 0.,
+            top: 0.,
+            right: 0.,
+            bottom: 0.,
+        };
+        handle_fail(unsafe { p::FPDF_GetPageBoundingBox(self.ptr, &mut rect) })?;
+        Ok((rect.left, rect.bottom, rect.right, rect.top))
+    }
+
     // Returns one of the following indicating the page rotation:
     //   0 - No rotation.
     //   1 - Rotated 90 degrees clockwise.
@@ -265,6 +636,25 @@ impl Page {
     pub fn generate_content(&self) -> Result<()> {
         handle_fail(unsafe { p::FPDFPage_GenerateContent(self.ptr) })
     }
+
+    // `subtype` is one of the FPDF_ANNOT_* constants.
+    pub fn create_annotation(&self, subtype: u32) -> Result<Annotation> {
+        let ptr = unsafe { p::FPDFPage_CreateAnnot(self.ptr, subtype as i32) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(Annotation { ptr })
+    }
+
+    pub fn load_text_page(&self) -> Result<TextPage> {
+        let ptr = unsafe { p::FPDFText_LoadPage(self.ptr) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::PageError);
+        }
+        Ok(TextPage { ptr })
+    }
 }
 
 impl Drop for Page {
@@ -273,6 +663,276 @@ impl Drop for Page {
     }
 }
 
+pub struct TextPage {
+    ptr: p::FPDF_TEXTPAGE,
+}
+
+impl TextPage {
+    pub fn char_count(&self) -> usize {
+        // Returns -1 on error.
+        unsafe { p::FPDFText_CountChars(self.ptr).max(0) as usize }
+    }
+
+    pub fn text(&self) -> String {
+        self.text_range(0, self.char_count())
+    }
+
+    // Line breaks are returned as "\r\n".
+    pub fn text_range(&self, start: usize, count: usize) -> String {
+        if count == 0 {
+            return String::new();
+        }
+        // Room for a null terminator.
+        let mut buf: Vec<u16> = vec![0; count + 1];
+        let len =
+            unsafe { p::FPDFText_GetText(self.ptr, start as i32, count as i32, buf.as_mut_ptr()) };
+        buf.truncate((len.max(1) - 1) as usize);
+        String::from_utf16_lossy(&buf)
+    }
+
+    // The text of the characters inside a rectangle, in points from the bottom left of the page.
+    pub fn bounded_text(&self, left: f64, top: f64, right: f64, bottom: f64) -> String {
+        let len = unsafe {
+            p::FPDFText_GetBoundedText(self.ptr, left, top, right, bottom, ptr::null_mut(), 0)
+        };
+        if len <= 0 {
+            return String::new();
+        }
+        // Room for a null terminator.
+        let mut buf: Vec<u16> = vec![0; len as usize + 1];
+        let len = unsafe {
+            p::FPDFText_GetBoundedText(
+                self.ptr,
+                left,
+                top,
+                right,
+                bottom,
+                buf.as_mut_ptr(),
+                buf.len() as i32,
+            )
+        };
+        buf.truncate(len.max(0) as usize);
+        while buf.last() == Some(&0) {
+            buf.pop();
+        }
+        String::from_utf16_lossy(&buf)
+    }
+
+    pub fn char(&self, pos: usize) -> TextChar {
+        let index = pos as i32;
+        unsafe {
+            let unicode = p::FPDFText_GetUnicode(self.ptr, index);
+            let (mut left, mut right, mut bottom, mut top) = (0., 0., 0., 0.);
+            p::FPDFText_GetCharBox(
+                self.ptr,
+                index,
+                &mut left,
+                &mut right,
+                &mut bottom,
+                &mut top,
+            );
+            let (mut origin_x, mut origin_y) = (0., 0.);
+            p::FPDFText_GetCharOrigin(self.ptr, index, &mut origin_x, &mut origin_y);
+            let (mut r, mut g, mut b, mut a) = (0, 0, 0, 0);
+            p::FPDFText_GetFillColor(self.ptr, index, &mut r, &mut g, &mut b, &mut a);
+
+            let mut font_flags = 0;
+            let len = p::FPDFText_GetFontInfo(self.ptr, index, ptr::null_mut(), 0, &mut font_flags);
+            let mut font_name = vec![0u8; len as usize];
+            if len > 0 {
+                p::FPDFText_GetFontInfo(
+                    self.ptr,
+                    index,
+                    font_name.as_mut_ptr() as *mut c_void,
+                    len,
+                    &mut font_flags,
+                );
+                // Drop the null terminator.
+                font_name.pop();
+            }
+
+            TextChar {
+                text: char::from_u32(unicode).unwrap_or(char::REPLACEMENT_CHARACTER),
+                generated: p::FPDFText_IsGenerated(self.ptr, index) == 1,
+                left,
+                right,
+                bottom,
+                top,
+                origin_x,
+                origin_y,
+                font_size: p::FPDFText_GetFontSize(self.ptr, index),
+                font_name: String::from_utf8_lossy(&font_name).into_owned(),
+                font_flags,
+                font_weight: p::FPDFText_GetFontWeight(self.ptr, index),
+                fill_color: [r as u8, g as u8, b as u8, a as u8],
+                angle: p::FPDFText_GetCharAngle(self.ptr, index),
+            }
+        }
+    }
+
+    pub fn chars(&self) -> Vec<TextChar> {
+        (0..self.char_count()).map(|pos| self.char(pos)).collect()
+    }
+
+    // `flags` is a combination of FPDF_MATCHCASE, FPDF_MATCHWHOLEWORD and FPDF_MATCHCONSECUTIVE.
+    pub fn search(&self, query: &str, flags: u32) -> Result<TextSearch> {
+        let w_query: Vec<u16> = query.encode_utf16().chain(Some(0)).collect();
+        let ptr = unsafe { p::FPDFText_FindStart(self.ptr, w_query.as_ptr(), flags as c_ulong, 0) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(TextSearch { ptr })
+    }
+
+    // The rectangles covering a range of characters - one per line or text run.
+    pub fn rects(&self, start: usize, count: usize) -> Vec<TextRect> {
+        let n = unsafe { p::FPDFText_CountRects(self.ptr, start as i32, count as i32) };
+        (0..n.max(0))
+            .filter_map(|i| {
+                let mut rect = TextRect::default();
+                let ok = unsafe {
+                    p::FPDFText_GetRect(
+                        self.ptr,
+                        i,
+                        &mut rect.left,
+                        &mut rect.top,
+                        &mut rect.right,
+                        &mut rect.bottom,
+                    )
+                };
+                if ok == 1 {
+                    Some(rect)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for TextPage {
+    fn drop(&mut self) {
+        unsafe { p::FPDFText_ClosePage(self.ptr) };
+    }
+}
+
+// A character's box is in points with the origin at the bottom left of the page.
+pub struct TextChar {
+    pub text: char,
+    // Characters like spaces and line breaks which PDFium inferred from the layout.
+    pub generated: bool,
+    pub left: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub top: f64,
+    // The start of the character's baseline.
+    pub origin_x: f64,
+    pub origin_y: f64,
+    pub font_size: f64,
+    pub font_name: String,
+    // Font descriptor flags - see section 5.7.1 of the PDF reference.
+    pub font_flags: i32,
+    // -1 if unknown.
+    pub font_weight: i32,
+    pub fill_color: [u8; 4],
+    // In radians - -1 on error.
+    pub angle: f32,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TextRect {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+// Must not outlive the text page it was started on.
+pub struct TextSearch {
+    ptr: p::FPDF_SCHHANDLE,
+}
+
+// Yields the start index and character count of each match.
+impl Iterator for TextSearch {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            if p::FPDFText_FindNext(self.ptr) != 1 {
+                return None;
+            }
+            let start = p::FPDFText_GetSchResultIndex(self.ptr);
+            let count = p::FPDFText_GetSchCount(self.ptr);
+            Some((start.max(0) as usize, count.max(0) as usize))
+        }
+    }
+}
+
+impl Drop for TextSearch {
+    fn drop(&mut self) {
+        unsafe { p::FPDFText_FindClose(self.ptr) };
+    }
+}
+
+pub struct Annotation {
+    ptr: p::FPDF_ANNOTATION,
+}
+
+impl Annotation {
+    pub fn set_color(&self, r: u8, g: u8, b: u8, a: u8) -> Result<()> {
+        handle_fail(unsafe {
+            p::FPDFAnnot_SetColor(
+                self.ptr,
+                p::FPDFANNOT_COLORTYPE_FPDFANNOT_COLORTYPE_Color,
+                r as u32,
+                g as u32,
+                b as u32,
+                a as u32,
+            )
+        })
+    }
+
+    pub fn set_rect(&self, rect: &TextRect) -> Result<()> {
+        let rect = p::FS_RECTF {
+            left: rect.left as f32,
+            top: rect.top as f32,
+            right: rect.right as f32,
+            bottom: rect.bottom as f32,
+        };
+        handle_fail(unsafe { p::FPDFAnnot_SetRect(self.ptr, &rect) })
+    }
+
+    // Adds a quadrilateral to the area covered by a markup annotation.
+    pub fn append_attachment_points(&self, rect: &TextRect) -> Result<()> {
+        let (left, top, right, bottom) = (
+            rect.left as f32,
+            rect.top as f32,
+            rect.right as f32,
+            rect.bottom as f32,
+        );
+        // The corners go top left, top right, bottom left, bottom right which, although the
+        // PDF reference says otherwise, is what viewers expect.
+        let quad = p::FS_QUADPOINTSF {
+            x1: left,
+            y1: top,
+            x2: right,
+            y2: top,
+            x3: left,
+            y3: bottom,
+            x4: right,
+            y4: bottom,
+        };
+        handle_fail(unsafe { p::FPDFAnnot_AppendAttachmentPoints(self.ptr, &quad) })
+    }
+}
+
+impl Drop for Annotation {
+    fn drop(&mut self) {
+        unsafe { p::FPDFPage_CloseAnnot(self.ptr) };
+    }
+}
+
 pub struct Object {
     ptr: p::FPDF_PAGEOBJECT,
 }
@@ -283,10 +943,36 @@ impl Object {
         handle_err()
     }
 
-    fn type_(&self) -> i32 {
+    // One of the FPDF_PAGEOBJ_* constants.
+    pub fn type_(&self) -> i32 {
         unsafe { p::FPDFPageObj_GetType(self.ptr) }
     }
 
+    // Returns (left, bottom, right, top) in points from the bottom left of the page, or of the
+    // form XObject the object is inside.
+    pub fn bounds(&self) -> Result<(f32, f32, f32, f32)> {
+        let (mut left, mut bottom, mut right, mut top) = (0., 0., 0., 0.);
+        handle_fail(unsafe {
+            p::FPDFPageObj_GetBounds(self.ptr, &mut left, &mut bottom, &mut right, &mut top)
+        })?;
+        Ok((left, bottom, right, top))
+    }
+
+    // Returns the matrix [a, b, c, d, e, f] mapping the object's coordinates to the page's, or to
+    // those of the form XObject it's inside.
+    pub fn matrix(&self) -> Result<[f32; 6]> {
+        let mut m = p::FS_MATRIX {
+            a: 1.,
+            b: 0.,
+            c: 0.,
+            d: 1.,
+            e: 0.,
+            f: 0.,
+        };
+        handle_fail(unsafe { p::FPDFPageObj_GetMatrix(self.ptr, &mut m) })?;
+        Ok([m.a, m.b, m.c, m.d, m.e, m.f])
+    }
+
     pub fn into_image(self) -> Option<ImageObject> {
         if self.type_() as u32 == p::FPDF_PAGEOBJ_IMAGE {
             Some(ImageObject { object: self })
@@ -294,6 +980,97 @@ impl Object {
             None
         }
     }
+
+    pub fn into_path(self) -> Option<PathObject> {
+        if self.type_() as u32 == p::FPDF_PAGEOBJ_PATH {
+            Some(PathObject { object: self })
+        } else {
+            None
+        }
+    }
+
+    pub fn into_text(self) -> Option<TextObject> {
+        if self.type_() as u32 == p::FPDF_PAGEOBJ_TEXT {
+            Some(TextObject { object: self })
+        } else {
+            None
+        }
+    }
+
+    // Returns the objects inside a form XObject, or nothing for other types of object.
+    pub fn form_objects(&self) -> Vec<Object> {
+        if self.type_() as u32 != p::FPDF_PAGEOBJ_FORM {
+            return vec![];
+        }
+        let count = unsafe { p::FPDFFormObj_CountObjects(self.ptr) };
+        (0..count.max(0))
+            .map(|pos| unsafe { p::FPDFFormObj_GetObject(self.ptr, pos as c_ulong) })
+            .filter(|ptr| !ptr.is_null())
+            .map(|ptr| Object { ptr })
+            .collect()
+    }
+}
+
+pub struct NestedObject {
+    pub object: Object,
+    // The index `Page::load_object` takes, for objects at the top level of the page.
+    pub pos: Option<usize>,
+    // Maps the coordinates of the object's bounds and matrix - those of the form XObject it's
+    // inside, if any - to the page's.
+    pub matrix: [f32; 6],
+}
+
+pub struct PathObject {
+    object: Object,
+}
+
+impl Deref for PathObject {
+    type Target = Object;
+
+    fn deref(&self) -> &Self::Target {
+        &self.object
+    }
+}
+
+impl PathObject {
+    // Returns whether the path is filled and whether it is stroked.
+    pub fn draw_mode(&self) -> Result<(bool, bool)> {
+        let (mut fill_mode, mut stroke) = (0, 0);
+        handle_fail(unsafe { p::FPDFPath_GetDrawMode(self.ptr, &mut fill_mode, &mut stroke) })?;
+        Ok((fill_mode as u32 != p::FPDF_FILLMODE_NONE, stroke == 1))
+    }
+
+    // Points are in the path's own coordinates - see `Object::matrix`.
+    pub fn segments(&self) -> Vec<PathSegment> {
+        let count = unsafe { p::FPDFPath_CountSegments(self.ptr) };
+        (0..count.max(0))
+            .filter_map(|i| unsafe {
+                let segment = p::FPDFPath_GetPathSegment(self.ptr, i);
+                if segment.is_null() {
+                    return None;
+                }
+                let (mut x, mut y) = (0., 0.);
+                if p::FPDFPathSegment_GetPoint(segment, &mut x, &mut y) != 1 {
+                    return None;
+                }
+                Some(PathSegment {
+                    x,
+                    y,
+                    kind: p::FPDFPathSegment_GetType(segment),
+                    close: p::FPDFPathSegment_GetClose(segment) == 1,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct PathSegment {
+    pub x: f32,
+    pub y: f32,
+    // One of the FPDF_SEGMENT_* constants.
+    pub kind: i32,
+    // Whether the segment closes the current subpath.
+    pub close: bool,
 }
 
 pub struct ImageObject {
@@ -338,12 +1115,86 @@ impl TextObject {
         let w_text = to_wide_string(if text.is_empty() { " " } else { text });
         handle_fail(unsafe { p::FPDFText_SetText(self.ptr, w_text.as_ptr() as p::FPDF_WIDESTRING) })
     }
+
+    pub fn font(&self) -> Result<Font> {
+        let ptr = unsafe { p::FPDFTextObj_GetFont(self.ptr) };
+        handle_err()?;
+        if ptr.is_null() {
+            return Err(PDFiumError::Unknown);
+        }
+        Ok(Font { ptr })
+    }
 }
 
 pub struct Font {
     ptr: p::FPDF_FONT,
 }
 
+impl Font {
+    // The PostScript name, including the tag of subset fonts - eg. "ABCDEF+Helvetica-Bold".
+    pub fn base_name(&self) -> String {
+        let len = unsafe { p::FPDFFont_GetBaseFontName(self.ptr, ptr::null_mut(), 0) };
+        font_string(len as usize, |buf| unsafe {
+            p::FPDFFont_GetBaseFontName(self.ptr, buf.as_mut_ptr() as *mut _, buf.len() as _);
+        })
+    }
+
+    pub fn family_name(&self) -> String {
+        let len = unsafe { p::FPDFFont_GetFamilyName(self.ptr, ptr::null_mut(), 0) };
+        font_string(len as usize, |buf| unsafe {
+            p::FPDFFont_GetFamilyName(self.ptr, buf.as_mut_ptr() as *mut _, buf.len() as _);
+        })
+    }
+
+    // Returns eg. 400 for regular or 700 for bold fonts, or None if the font doesn't say.
+    pub fn weight(&self) -> Option<i32> {
+        let weight = unsafe { p::FPDFFont_GetWeight(self.ptr) };
+        if weight < 0 {
+            None
+        } else {
+            Some(weight)
+        }
+    }
+
+    // In degrees counter-clockwise from vertical, so negative for fonts which slant right.
+    pub fn italic_angle(&self) -> Option<i32> {
+        let mut angle = 0;
+        let ok = unsafe { p::FPDFFont_GetItalicAngle(self.ptr, &mut angle) };
+        if ok == 1 {
+            Some(angle)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_embedded(&self) -> bool {
+        unsafe { p::FPDFFont_GetIsEmbedded(self.ptr) == 1 }
+    }
+
+    // Returns the decoded font program of an embedded font.
+    pub fn data(&self) -> Result<Vec<u8>> {
+        let mut len = 0;
+        handle_fail(unsafe { p::FPDFFont_GetFontData(self.ptr, ptr::null_mut(), 0, &mut len) })?;
+        let mut buf: Vec<u8> = vec![0; len as usize];
+        handle_fail(unsafe {
+            p::FPDFFont_GetFontData(self.ptr, buf.as_mut_ptr(), buf.len() as _, &mut len)
+        })?;
+        buf.truncate(len as usize);
+        Ok(buf)
+    }
+}
+
+// Font names are null terminated and Latin-1 or UTF-8. `len` includes the terminator.
+fn font_string(len: usize, get: impl FnOnce(&mut [u8])) -> String {
+    if len <= 1 {
+        return String::new();
+    }
+    let mut buf: Vec<u8> = vec![0; len];
+    get(&mut buf);
+    buf.pop();
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
 pub struct Bitmap {
     ptr: p::FPDF_BITMAP,
 }
@@ -399,13 +1250,142 @@ impl Bitmap {
         unsafe { p::FPDFBitmap_GetFormat(self.ptr) as u32 }
     }
 
-    pub fn render_page(&self, page: &Page, width: i32, height: i32, rotation: i32) {
+    // `background` is an 0xAARRGGBB color - the bitmap is left untouched (ie. transparent
+    // for zero-initialized BGRA bitmaps) if unspecified. `flags` are the FPDF_* render flags
+    // defined in fpdfview.h.
+    pub fn render_page(
+        &self,
+        page: &Page,
+        width: i32,
+        height: i32,
+        rotation: i32,
+        background: Option<u32>,
+        flags: i32,
+    ) {
+        unsafe {
+            if let Some(background) = background {
+                p::FPDFBitmap_FillRect(self.ptr, 0, 0, width, height, background as p::FPDF_DWORD);
+            }
+            p::FPDF_RenderPageBitmap(self.ptr, page.ptr, 0, 0, width, height, rotation, flags);
+        }
+    }
+
+    // Renders the page like `render_page` but gives up once `deadline` has passed, returning
+    // `false` if so. The bitmap may be partially rendered in that case.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_page_progressive(
+        &self,
+        page: &Page,
+        width: i32,
+        height: i32,
+        rotation: i32,
+        background: Option<u32>,
+        flags: i32,
+        deadline: Instant,
+    ) -> Result<bool> {
+        // PDFium checks this between chunks of work and pauses rendering if it returns true.
+        unsafe extern "C" fn need_to_pause_now(pause: *mut p::IFSDK_PAUSE) -> p::FPDF_BOOL {
+            let deadline = &*((*pause).user as *const Instant);
+            (Instant::now() >= *deadline) as p::FPDF_BOOL
+        }
+
+        let mut pause = p::IFSDK_PAUSE {
+            version: 1,
+            NeedToPauseNow: Some(need_to_pause_now),
+            user: &deadline as *const Instant as *mut c_void,
+        };
+        unsafe {
+            if let Some(background) = background {
+                p::FPDFBitmap_FillRect(self.ptr, 0, 0, width, height, background as p::FPDF_DWORD);
+            }
+            let mut status = p::FPDF_RenderPageBitmap_Start(
+                self.ptr, page.ptr, 0, 0, width, height, rotation, flags, &mut pause,
+            );
+            while status as u32 == p::FPDF_RENDER_TOBECONTINUED && Instant::now() < deadline {
+                status = p::FPDF_RenderPage_Continue(page.ptr, &mut pause);
+            }
+            p::FPDF_RenderPage_Close(page.ptr);
+
+            match status as u32 {
+                p::FPDF_RENDER_DONE => Ok(true),
+                p::FPDF_RENDER_TOBECONTINUED => Ok(false),
+                _ => Err(handle_err().err().unwrap_or(PDFiumError::Unknown)),
+            }
+        }
+    }
+
+    // Renders the page transformed by `matrix` (a, b, c, d, e, f), clipped to `width` x `height`.
+    // The matrix maps from page space in points - with the origin at the top left of the
+    // (rotated) page - to bitmap pixels.
+    pub fn render_page_with_matrix(
+        &self,
+        page: &Page,
+        matrix: [f32; 6],
+        width: i32,
+        height: i32,
+        background: Option<u32>,
+        flags: i32,
+    ) {
+        let matrix = p::FS_MATRIX {
+            a: matrix[0],
+            b: matrix[1],
+            c: matrix[2],
+            d: matrix[3],
+            e: matrix[4],
+            f: matrix[5],
+        };
+        let clip = p::FS_RECTF {
+            left: 0.,
+            top: 0.,
+            right: width as f32,
+            bottom: height as f32,
+        };
         unsafe {
-            p::FPDFBitmap_FillRect(self.ptr, 0, 0, width, height, 0xffffff);
-            p::FPDF_RenderPageBitmap(self.ptr, page.ptr, 0, 0, width, height, rotation, 0);
+            if let Some(background) = background {
+                p::FPDFBitmap_FillRect(self.ptr, 0, 0, width, height, background as p::FPDF_DWORD);
+            }
+            p::FPDF_RenderPageBitmapWithMatrix(self.ptr, page.ptr, &matrix, &clip, flags);
         }
     }
 
+    // Copies the bitmap into an RGB(A) image, dropping any stride padding.
+    pub fn to_image(&self) -> anyhow::Result<DynamicImage> {
+        let (width, height) = (self.width() as u32, self.height() as u32);
+        let stride = self.stride() as usize;
+        let buf = self.buffer();
+        let channels = match self.format() {
+            p::FPDFBitmap_Gray => 1,
+            p::FPDFBitmap_BGR => 3,
+            p::FPDFBitmap_BGRx | p::FPDFBitmap_BGRA => 4,
+            _ => return Err(anyhow!("unknown image format")),
+        };
+        let rows = buf
+            .chunks(stride)
+            .take(height as usize)
+            .map(|row| &row[..width as usize * channels]);
+        let img = match self.format() {
+            p::FPDFBitmap_Gray => {
+                let bytes = rows.flatten().copied().collect();
+                DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, bytes).unwrap())
+            }
+            p::FPDFBitmap_BGR | p::FPDFBitmap_BGRx => {
+                let bytes = rows
+                    .flat_map(|row| row.chunks(channels))
+                    .flat_map(|px| [px[2], px[1], px[0]])
+                    .collect();
+                DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, bytes).unwrap())
+            }
+            _ => {
+                let bytes = rows
+                    .flat_map(|row| row.chunks(channels))
+                    .flat_map(|px| [px[2], px[1], px[0], px[3]])
+                    .collect();
+                DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, bytes).unwrap())
+            }
+        };
+        Ok(img)
+    }
+
     // `libjpegturbo` is about 4x faster than rust's `image`.
     pub fn write_image(&self, path: impl AsRef<Path>, quality: u8) -> anyhow::Result<()> {
         unsafe {
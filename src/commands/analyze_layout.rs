@@ -0,0 +1,69 @@
+use super::{resolve_labels, Command};
+use crate::bindings::Document;
+use crate::layout::{self, Region};
+use crate::syntax::PageSelection;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// split the pages of a PDF into headings, paragraphs, images, figures and tables and print
+/// them as JSON in reading order
+#[derive(Args)]
+pub struct AnalyzeLayoutCommand {
+    /// pages to analyze
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct PageLayout {
+    page: usize,
+    width: f32,
+    height: f32,
+    regions: Vec<OrderedRegion>,
+}
+
+#[derive(Serialize)]
+struct OrderedRegion {
+    // Position in reading order, from 0.
+    order: usize,
+    #[serde(flatten)]
+    region: Region,
+}
+
+impl Command for AnalyzeLayoutCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut layouts = vec![];
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let text_page = page.load_text_page()?;
+            let regions = layout::analyze(&page, &text_page)
+                .into_iter()
+                .enumerate()
+                .map(|(order, region)| OrderedRegion { order, region })
+                .collect();
+            layouts.push(PageLayout {
+                page: pos,
+                width: page.width(),
+                height: page.height(),
+                regions,
+            });
+        }
+
+        println!("{}", serde_json::to_string_pretty(&layouts)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use super::Command;
+use crate::bindings::Document;
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// list, extract, add or delete files attached to a PDF
+#[derive(Args)]
+pub struct AttachmentsCommand {
+    #[clap(subcommand)]
+    command: AttachmentsSubcommand,
+}
+
+#[derive(Subcommand)]
+enum AttachmentsSubcommand {
+    List(ListAttachmentsCommand),
+    Extract(ExtractAttachmentsCommand),
+    Add(AddAttachmentsCommand),
+    Delete(DeleteAttachmentsCommand),
+}
+
+impl Command for AttachmentsCommand {
+    fn execute(self) -> Result<()> {
+        match self.command {
+            AttachmentsSubcommand::List(c) => c.execute(),
+            AttachmentsSubcommand::Extract(c) => c.execute(),
+            AttachmentsSubcommand::Add(c) => c.execute(),
+            AttachmentsSubcommand::Delete(c) => c.execute(),
+        }
+    }
+}
+
+/// list the attachments of a PDF as JSON
+#[derive(Args)]
+pub struct ListAttachmentsCommand {
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct AttachmentInfo {
+    name: String,
+    // In bytes, or None if the file isn't embedded.
+    size: Option<usize>,
+    // The hex encoded MD5 digest of the file, if the PDF has one.
+    checksum: Option<String>,
+}
+
+impl Command for ListAttachmentsCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let mut attachments = vec![];
+        for pos in 0..doc.attachment_count() {
+            let attachment = doc.load_attachment(pos)?;
+            attachments.push(AttachmentInfo {
+                name: attachment.name(),
+                size: attachment.file().ok().map(|data| data.len()),
+                checksum: attachment.string_value("CheckSum"),
+            });
+        }
+
+        println!("{}", serde_json::to_string_pretty(&attachments)?);
+
+        Ok(())
+    }
+}
+
+/// extract the attachments of a PDF
+#[derive(Args)]
+pub struct ExtractAttachmentsCommand {
+    /// only extract the attachment with this name - may be repeated
+    #[clap(long)]
+    name: Vec<String>,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to a directory where the attachments will be written
+    out_dir: PathBuf,
+}
+
+impl Command for ExtractAttachmentsCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        fs::create_dir_all(&self.out_dir)?;
+
+        let mut found = vec![];
+        let mut file_names = HashSet::new();
+        let mut failures = 0;
+        for pos in 0..doc.attachment_count() {
+            let attachment = doc.load_attachment(pos)?;
+            let name = attachment.name();
+            if !self.name.is_empty() && !self.name.contains(&name) {
+                continue;
+            }
+            found.push(name.clone());
+            // Names come from the PDF so only their last component is used, keeping the
+            // files inside the output directory.
+            let file_name = match unique_file_name(&name, &mut file_names) {
+                Some(file_name) => file_name,
+                None => {
+                    eprintln!("{}: not a valid file name", name);
+                    failures += 1;
+                    continue;
+                }
+            };
+            match attachment.file() {
+                Ok(data) => fs::write(self.out_dir.join(file_name), data)?,
+                Err(err) => {
+                    eprintln!("{}: {}", name, err);
+                    failures += 1;
+                }
+            }
+        }
+
+        if let Some(name) = self.name.iter().find(|name| !found.contains(name)) {
+            return Err(anyhow!("no attachment named {}", name));
+        }
+        if failures > 0 {
+            return Err(anyhow!(
+                "failed to extract {} of {} attachments",
+                failures,
+                found.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Several attachments can share a name, or the same last component, so a number is added to
+// the names which are already in `file_names`, eg. "data_2.csv".
+fn unique_file_name(name: &str, file_names: &mut HashSet<String>) -> Option<String> {
+    let path = Path::new(name);
+    let file_name = path.file_name()?.to_string_lossy().into_owned();
+    if file_names.insert(file_name.clone()) {
+        return Some(file_name);
+    }
+    let stem = path.file_stem()?.to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 2;
+    loop {
+        let file_name = format!("{}_{}{}", stem, n, ext);
+        if file_names.insert(file_name.clone()) {
+            return Some(file_name);
+        }
+        n += 1;
+    }
+}
+
+/// attach files to a PDF
+#[derive(Args)]
+pub struct AddAttachmentsCommand {
+    /// name to attach the file as, rather than its file name - only valid with a single file
+    #[clap(long)]
+    name: Option<String>,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to write the resulting PDF
+    out: PathBuf,
+    /// paths to the files to attach
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+impl Command for AddAttachmentsCommand {
+    fn execute(self) -> Result<()> {
+        if self.name.is_some() && self.files.len() > 1 {
+            return Err(anyhow!(
+                "--name can only be used when attaching a single file"
+            ));
+        }
+
+        let doc = Document::load(&self.pdf)?;
+
+        for path in self.files.iter() {
+            let name = match self.name.as_ref() {
+                Some(name) => name.clone(),
+                None => path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("{} has no file name", path.display()))?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            let contents = fs::read(path)?;
+            let attachment = doc
+                .add_attachment(&name)
+                .map_err(|_| anyhow!("failed to attach {} - is the name already used?", name))?;
+            attachment
+                .set_file(&doc, &contents)
+                .map_err(|_| anyhow!("failed to attach {}", path.display()))?;
+        }
+
+        doc.save(&mut File::create(&self.out)?)?;
+
+        Ok(())
+    }
+}
+
+/// delete attachments from a PDF
+#[derive(Args)]
+pub struct DeleteAttachmentsCommand {
+    /// name of an attachment to delete - may be repeated
+    #[clap(long, required = true)]
+    name: Vec<String>,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to write the resulting PDF
+    out: PathBuf,
+}
+
+impl Command for DeleteAttachmentsCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        for name in self.name.iter() {
+            // Deleting an attachment moves the ones after it down so they're looked up again
+            // each time.
+            let pos = (0..doc.attachment_count())
+                .find(|&pos| {
+                    doc.load_attachment(pos)
+                        .is_ok_and(|attachment| attachment.name() == *name)
+                })
+                .ok_or_else(|| anyhow!("no attachment named {}", name))?;
+            doc.delete_attachment(pos)?;
+        }
+
+        doc.save(&mut File::create(&self.out)?)?;
+
+        Ok(())
+    }
+}
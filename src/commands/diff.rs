@@ -0,0 +1,196 @@
+use super::{resolve_labels, Command};
+use crate::bindings::{Bitmap, Document};
+use crate::syntax::{Color, PageSelection};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use image::{Rgb, RgbImage};
+use pdfium_sys as p;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// compare the rendered pages of two PDFs and print a JSON summary - fails if any pages differ
+#[derive(Args)]
+pub struct DiffCommand {
+    /// pages to compare - the same page numbers are compared in both PDFs, so page labels
+    /// (eg. label:iv) refer to the labels of the first PDF
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// dots/pixels per inch to render both PDFs at
+    #[clap(long, default_value_t = 100)]
+    dpi: u32,
+    /// pixels whose channels differ by more than this (0-255) count as changed
+    #[clap(long, default_value_t = 16)]
+    threshold: u8,
+    /// path to the original PDF
+    a: PathBuf,
+    /// path to the modified PDF
+    b: PathBuf,
+    /// path to a directory where difference images will be written
+    out_dir: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    changed_pages: Vec<usize>,
+    pages: Vec<PageDiff>,
+}
+
+#[derive(Serialize)]
+struct PageDiff {
+    page: usize,
+    changed: bool,
+    // Which PDF the page is missing from, if either.
+    missing: Option<&'static str>,
+    changed_pixels: u64,
+    // Changed pixels / total pixels.
+    diff_ratio: f64,
+    // Bounding box of the changed pixels as [x, y, width, height].
+    bounds: Option<[u32; 4]>,
+    image: Option<PathBuf>,
+}
+
+impl Command for DiffCommand {
+    fn execute(self) -> Result<()> {
+        let doc_a = Document::load(&self.a)?;
+        let doc_b = Document::load(&self.b)?;
+
+        fs::create_dir_all(&self.out_dir)?;
+
+        let page_count = doc_a.page_count().max(doc_b.page_count());
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            // Pages are compared by number so the second PDF's labels aren't used.
+            resolve_labels(&doc_a, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut summary = Summary {
+            changed_pages: vec![],
+            pages: vec![],
+        };
+        for pos in pages.iter(page_count) {
+            let in_a = pos >= 1 && pos <= doc_a.page_count();
+            let in_b = pos >= 1 && pos <= doc_b.page_count();
+            let diff = if !in_a && !in_b {
+                return Err(anyhow!("page {} is in neither PDF", pos));
+            } else if !in_a || !in_b {
+                PageDiff {
+                    page: pos,
+                    changed: true,
+                    missing: Some(if in_a { "b" } else { "a" }),
+                    changed_pixels: 0,
+                    diff_ratio: 1.,
+                    bounds: None,
+                    image: None,
+                }
+            } else {
+                let a = render(&doc_a, pos, self.dpi)?;
+                let b = render(&doc_b, pos, self.dpi)?;
+                self.compare(pos, &a, &b)?
+            };
+            if diff.changed {
+                summary.changed_pages.push(pos);
+            }
+            summary.pages.push(diff);
+        }
+
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+
+        // Fail so that diff can be used to check for regressions.
+        if !summary.changed_pages.is_empty() {
+            return Err(anyhow!(
+                "{} of {} pages differ",
+                summary.changed_pages.len(),
+                summary.pages.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl DiffCommand {
+    // Writes an image of `a` faded out with changed pixels in red if there are any.
+    fn compare(&self, pos: usize, a: &RgbImage, b: &RgbImage) -> Result<PageDiff> {
+        let width = a.width().max(b.width());
+        let height = a.height().max(b.height());
+        let mut out = RgbImage::new(width, height);
+        let mut changed_pixels = 0;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+
+        for y in 0..height {
+            for x in 0..width {
+                let px_a = pixel(a, x, y);
+                let px_b = pixel(b, x, y);
+                let changed = match (px_a, px_b) {
+                    (Some(px_a), Some(px_b)) => {
+                        px_a.0.iter().zip(px_b.0.iter()).any(|(c_a, c_b)| {
+                            (*c_a as i16 - *c_b as i16).abs() > self.threshold as i16
+                        })
+                    }
+                    _ => true,
+                };
+                if changed {
+                    changed_pixels += 1;
+                    min_x = min_x.min(x);
+                    min_y = min_y.min(y);
+                    max_x = max_x.max(x);
+                    max_y = max_y.max(y);
+                    out.put_pixel(x, y, Rgb([255, 0, 0]));
+                } else {
+                    let faded = px_a.unwrap().0.map(|c| 255 - (255 - c) / 4);
+                    out.put_pixel(x, y, Rgb(faded));
+                }
+            }
+        }
+
+        let changed = changed_pixels > 0;
+        let image = if changed {
+            let path = self
+                .out_dir
+                .join(format!(
+                    "{}_{}_diff",
+                    self.a.file_stem().unwrap().to_str().unwrap(),
+                    pos
+                ))
+                .with_extension("png");
+            out.save_with_format(&path, image::ImageFormat::Png)?;
+            Some(path)
+        } else {
+            None
+        };
+
+        Ok(PageDiff {
+            page: pos,
+            changed,
+            missing: None,
+            changed_pixels,
+            diff_ratio: changed_pixels as f64 / (width as f64 * height as f64),
+            bounds: if changed {
+                Some([min_x, min_y, max_x - min_x + 1, max_y - min_y + 1])
+            } else {
+                None
+            },
+            image,
+        })
+    }
+}
+
+fn render(doc: &Document, pos: usize, dpi: u32) -> Result<RgbImage> {
+    let page = doc.load_page(pos - 1)?;
+    // 72 points per inch.
+    let width = (page.width() / 72. * dpi as f32).round() as i32;
+    let height = (page.height() / 72. * dpi as f32).round() as i32;
+    let bmp = Bitmap::new(width, height, p::FPDFBitmap_BGR)?;
+    bmp.render_page(&page, width, height, 0, Some(Color::WHITE.argb()), 0);
+    Ok(bmp.to_image()?.into_rgb8())
+}
+
+fn pixel(img: &RgbImage, x: u32, y: u32) -> Option<Rgb<u8>> {
+    if x < img.width() && y < img.height() {
+        Some(*img.get_pixel(x, y))
+    } else {
+        None
+    }
+}
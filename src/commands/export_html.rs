@@ -0,0 +1,293 @@
+use super::{load_image, resolve_labels, Command, ImageSizeArgs};
+use crate::bindings::{Bitmap, Document, Page};
+use crate::encoder::{self, Encoder};
+use crate::layout;
+use crate::syntax::{Color, ImageFormat, PageSelection};
+use crate::text::{self, Word};
+use anyhow::Result;
+use clap::Args;
+use pdfium_sys as p;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+/// export each page of a PDF to an HTML file with positioned, selectable text
+#[derive(Args)]
+pub struct ExportHtmlCommand {
+    /// pages to export
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// render each page as a background image with the text laid invisibly over it, which
+    /// looks like the original but only as sharp as --dpi
+    #[clap(long)]
+    background: bool,
+    /// dots/pixels per inch to render backgrounds at
+    #[clap(long, default_value_t = 150)]
+    dpi: u32,
+    /// image format - jpg, png, tiff, pnm, bmp, webp or avif
+    #[clap(long, default_value = "png")]
+    format: ImageFormat,
+    /// JPEG, WebP and AVIF quality argument
+    #[clap(long, default_value_t = 92)]
+    quality: u8,
+    #[clap(flatten)]
+    size: ImageSizeArgs,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to a directory where the HTML files and images will be written
+    out_dir: PathBuf,
+}
+
+// Line heights are 1em and the baseline of most fonts sits about this far down.
+const BASELINE: f64 = 0.8;
+// Font descriptor flag - see section 5.7.1 of the PDF reference.
+const ITALIC: i32 = 1 << 6;
+
+const STYLE: &str = "\
+body { margin: 0; padding: 1em 0; background: #888; }
+.page { position: relative; margin: 0 auto; overflow: hidden; background: white; }
+.page > img { position: absolute; left: 0; top: 0; width: 100%; height: 100%; }
+.content { position: absolute; left: 0; top: 0; transform-origin: 0 0; }
+.content img { position: absolute; }
+.content span { position: absolute; white-space: pre; line-height: 1; transform-origin: 0 0.8em; }
+.background span { color: transparent !important; }
+";
+
+// Words are stretched to their width in the PDF since the browser's fonts won't match.
+const SCRIPT: &str = "\
+document.querySelectorAll('span[data-w]').forEach(function (el) {
+  var text = el.firstChild, range = document.createRange();
+  range.setStart(text, 0);
+  range.setEnd(text, text.length - 1);
+  var scale = el.dataset.w * 4 / 3 / range.getBoundingClientRect().width;
+  el.style.transform = 'rotate(' + -(el.dataset.a || 0) + 'rad) scaleX(' + scale + ')';
+});
+";
+
+impl Command for ExportHtmlCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        fs::create_dir_all(&self.out_dir)?;
+
+        let encoder = encoder::new(
+            self.format,
+            &encoder::Options {
+                quality: self.quality,
+                ..Default::default()
+            },
+        );
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let html = self.export_page(&doc, &page, pos, encoder.as_ref())?;
+            let html_path = self
+                .out_dir
+                .join(format!("{}_{}", self.file_stem(), pos))
+                .with_extension("html");
+            fs::write(html_path, html)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ExportHtmlCommand {
+    fn file_stem(&self) -> &str {
+        self.pdf.file_stem().unwrap().to_str().unwrap()
+    }
+
+    fn export_page(
+        &self,
+        doc: &Document,
+        page: &Page,
+        pos: usize,
+        encoder: &dyn Encoder,
+    ) -> Result<String> {
+        let (width, height) = (page.width() as f64, page.height() as f64);
+        let rotation = page.rotation();
+        // Text and image positions are in unrotated page space so the content is laid out
+        // unrotated then turned the way up the page is displayed.
+        let (unrotated_width, unrotated_height, transform) = match rotation {
+            1 => (
+                height,
+                width,
+                format!("translateX({}pt) rotate(90deg)", width),
+            ),
+            2 => (
+                width,
+                height,
+                format!("translate({}pt, {}pt) rotate(180deg)", width, height),
+            ),
+            3 => (
+                height,
+                width,
+                format!("translateY({}pt) rotate(270deg)", height),
+            ),
+            _ => (width, height, String::from("none")),
+        };
+
+        let mut html = String::new();
+        writeln!(html, "<!DOCTYPE html>")?;
+        writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(
+            html,
+            "<title>{} - page {}</title>",
+            escape(self.file_stem()),
+            pos
+        )?;
+        writeln!(html, "<style>\n{}</style>\n</head>\n<body>", STYLE)?;
+        writeln!(
+            html,
+            "<div class=\"page{}\" style=\"width: {:.2}pt; height: {:.2}pt\">",
+            if self.background { " background" } else { "" },
+            width,
+            height
+        )?;
+
+        if self.background {
+            let scale = self.dpi as f64 / 72.;
+            let (bmp_width, bmp_height) = (
+                (width * scale).round() as i32,
+                (height * scale).round() as i32,
+            );
+            let bmp = Bitmap::new(bmp_width, bmp_height, p::FPDFBitmap_BGR)?;
+            bmp.render_page(page, bmp_width, bmp_height, 0, Some(Color::WHITE.argb()), 0);
+            let name = self.write_image(encoder, &bmp, pos, "background")?;
+            writeln!(html, "<img src=\"{}\" alt=\"\">", escape(&name))?;
+        }
+
+        writeln!(
+            html,
+            "<div class=\"content\" style=\"width: {:.2}pt; height: {:.2}pt; transform: {}\">",
+            unrotated_width, unrotated_height, transform
+        )?;
+
+        // Images are part of the background when there is one.
+        if !self.background {
+            let mut image_count = 0;
+            for obj_pos in 0..page.object_count() {
+                let bmp = match load_image(doc, page, obj_pos, &self.size)? {
+                    Some(bmp) => bmp,
+                    None => continue,
+                };
+                let (left, bottom, right, top) = page.load_object(obj_pos)?.bounds()?;
+                image_count += 1;
+                let name =
+                    self.write_image(encoder, &bmp, pos, &format!("image_{}", image_count))?;
+                writeln!(
+                    html,
+                    "<img src=\"{}\" alt=\"\" style=\"left: {:.2}pt; top: {:.2}pt; width: {:.2}pt; height: {:.2}pt\">",
+                    escape(&name),
+                    left,
+                    unrotated_height - top as f64,
+                    right - left,
+                    top - bottom
+                )?;
+            }
+        }
+
+        let text_page = page.load_text_page()?;
+        for line in text::lines(&text_page) {
+            for word in line.words.iter() {
+                write_word(&mut html, word, unrotated_height)?;
+            }
+        }
+
+        writeln!(html, "</div>\n</div>")?;
+        writeln!(html, "<script>\n{}</script>\n</body>\n</html>", SCRIPT)?;
+        Ok(html)
+    }
+
+    // Returns the file name of the image, relative to the HTML.
+    fn write_image(
+        &self,
+        encoder: &dyn Encoder,
+        bmp: &Bitmap,
+        pos: usize,
+        name: &str,
+    ) -> Result<String> {
+        let file_name = format!(
+            "{}_{}_{}.{}",
+            self.file_stem(),
+            pos,
+            name,
+            self.format.extension()
+        );
+        encoder.encode(bmp, &self.out_dir.join(&file_name))?;
+        Ok(file_name)
+    }
+}
+
+// Words are positioned individually at the baseline of their first character, each followed
+// by a space so that copied text reads naturally.
+fn write_word(html: &mut String, word: &Word, page_height: f64) -> Result<()> {
+    let c = &word.chars[0];
+    let [r, g, b, a] = c.fill_color;
+    write!(
+        html,
+        "<span style=\"left: {:.2}pt; top: {:.2}pt; font: {}{}{:.2}pt {}; color: rgba({}, {}, {}, {:.2})\" data-w=\"{:.2}\"",
+        c.origin.x,
+        page_height - c.origin.y - c.font_size * BASELINE,
+        if c.font_flags & ITALIC != 0 || c.font_name.contains("Italic") || c.font_name.contains("Oblique") {
+            "italic "
+        } else {
+            ""
+        },
+        if layout::is_bold(word) { "bold " } else { "" },
+        c.font_size,
+        font_family(&c.font_name),
+        r,
+        g,
+        b,
+        a as f64 / 255.,
+        word.bounds.right() - c.origin.x,
+    )?;
+    if c.angle > 0. {
+        write!(html, " data-a=\"{:.4}\"", c.angle)?;
+    }
+    writeln!(html, ">{} </span>", escape(&word.text))?;
+    Ok(())
+}
+
+// Picks a generic family, falling back from the PDF font's own name in case it's installed.
+fn font_family(font_name: &str) -> String {
+    let name = text::strip_subset_tag(font_name).unwrap_or(font_name);
+    let family = name.split(['-', ',']).next().unwrap_or(name);
+    let lower = name.to_lowercase();
+    let generic = if lower.contains("courier") || lower.contains("mono") {
+        "monospace"
+    } else if lower.contains("times")
+        || (lower.contains("serif") && !lower.contains("sans"))
+        || lower.contains("roman")
+        || lower.contains("georgia")
+        || lower.contains("garamond")
+    {
+        "serif"
+    } else {
+        "sans-serif"
+    };
+    format!("'{}', {}", escape(&family.replace('\'', "")), generic)
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,247 @@
+use super::{load_image, region_matrix, resolve_labels, Command, ImageSizeArgs};
+use crate::bindings::{Bitmap, Document, Page};
+use crate::encoder::{self, Encoder};
+use crate::layout::{self, Region, RegionKind};
+use crate::syntax::{Color, ImageFormat, PageSelection};
+use anyhow::Result;
+use clap::Args;
+use pdfium_sys as p;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// export a PDF to Markdown, keeping its headings, paragraphs, lists, tables and images
+///
+/// Images and figures are written next to the Markdown file.
+#[derive(Args)]
+pub struct ExportMarkdownCommand {
+    /// pages to export
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// image format - jpg, png, tiff, pnm, bmp, webp or avif
+    #[clap(long, default_value = "png")]
+    format: ImageFormat,
+    /// JPEG, WebP and AVIF quality argument
+    #[clap(long, default_value_t = 92)]
+    quality: u8,
+    /// dots/pixels per inch to render figures at
+    #[clap(long, default_value_t = 150)]
+    dpi: u32,
+    #[clap(flatten)]
+    size: ImageSizeArgs,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to write the Markdown to
+    out: PathBuf,
+}
+
+impl Command for ExportMarkdownCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let out_dir = self.out.parent().unwrap_or_else(|| Path::new(""));
+        if !out_dir.as_os_str().is_empty() {
+            fs::create_dir_all(out_dir)?;
+        }
+
+        let encoder = encoder::new(
+            self.format,
+            &encoder::Options {
+                quality: self.quality,
+                ..Default::default()
+            },
+        );
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut exporter = Exporter {
+            command: &self,
+            encoder,
+            out_dir,
+            markdown: String::new(),
+            image_count: 0,
+            figure_count: 0,
+            list_item: 0,
+        };
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let text_page = page.load_text_page()?;
+            for region in layout::analyze(&page, &text_page) {
+                exporter.write_region(&doc, &page, &region)?;
+            }
+        }
+
+        fs::write(&self.out, exporter.markdown)?;
+
+        Ok(())
+    }
+}
+
+struct Exporter<'a> {
+    command: &'a ExportMarkdownCommand,
+    encoder: Box<dyn Encoder>,
+    out_dir: &'a Path,
+    markdown: String,
+    image_count: usize,
+    figure_count: usize,
+    // The number of the previous item of an ordered list, or 0 outside of lists.
+    list_item: usize,
+}
+
+impl Exporter<'_> {
+    fn write_region(&mut self, doc: &Document, page: &Page, region: &Region) -> Result<()> {
+        let text = region.text.as_deref().unwrap_or("");
+
+        if region.kind == RegionKind::ListItem {
+            if let Some((ordered, rest)) = layout::list_marker(text) {
+                // Items of the same list aren't separated by blank lines.
+                if self.list_item == 0 {
+                    self.start_block();
+                }
+                self.list_item += 1;
+                if ordered {
+                    self.markdown.push_str(&format!("{}. ", self.list_item));
+                } else {
+                    self.markdown.push_str("- ");
+                }
+                self.markdown.push_str(&escape(rest));
+                self.markdown.push('\n');
+                return Ok(());
+            }
+        }
+        self.list_item = 0;
+
+        match region.kind {
+            RegionKind::Heading => {
+                self.start_block();
+                let level = region.level.unwrap_or(1) as usize;
+                self.markdown.push_str(&"#".repeat(level));
+                self.markdown.push(' ');
+                self.markdown.push_str(&escape(text));
+                self.markdown.push('\n');
+            }
+            RegionKind::Paragraph | RegionKind::ListItem => {
+                self.start_block();
+                self.markdown.push_str(&escape(text));
+                self.markdown.push('\n');
+            }
+            RegionKind::Table => {
+                if let Some(rows) = region.rows.as_ref().filter(|rows| !rows.is_empty()) {
+                    self.start_block();
+                    self.write_table(rows);
+                }
+            }
+            RegionKind::Image => {
+                // Images inside form XObjects can't be loaded by index so they're rendered.
+                let bmp = match region.object {
+                    Some(obj_pos) => load_image(doc, page, obj_pos, &self.command.size)?,
+                    None => Some(self.render_figure(page, region)?),
+                };
+                if let Some(bmp) = bmp {
+                    self.image_count += 1;
+                    let name = format!("image_{}", self.image_count);
+                    self.write_image(&bmp, &name)?;
+                }
+            }
+            RegionKind::Figure => {
+                self.figure_count += 1;
+                let bmp = self.render_figure(page, region)?;
+                let name = format!("figure_{}", self.figure_count);
+                self.write_image(&bmp, &name)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Blocks are separated by blank lines.
+    fn start_block(&mut self) {
+        if !self.markdown.is_empty() {
+            self.markdown.push('\n');
+        }
+    }
+
+    fn write_table(&mut self, rows: &[Vec<String>]) {
+        let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for (i, row) in rows.iter().enumerate() {
+            self.markdown.push('|');
+            for col in 0..cols {
+                let cell = row.get(col).map_or("", String::as_str);
+                self.markdown.push(' ');
+                self.markdown
+                    .push_str(&escape(cell).replace('|', "\\|").replace('\n', " "));
+                self.markdown.push_str(" |");
+            }
+            self.markdown.push('\n');
+            // Markdown tables always have a header row.
+            if i == 0 {
+                self.markdown.push('|');
+                self.markdown.push_str(&" --- |".repeat(cols));
+                self.markdown.push('\n');
+            }
+        }
+    }
+
+    fn write_image(&mut self, bmp: &Bitmap, name: &str) -> Result<()> {
+        let file_name = format!(
+            "{}_{}.{}",
+            self.command.out.file_stem().unwrap().to_str().unwrap(),
+            name,
+            self.command.format.extension()
+        );
+        self.encoder.encode(bmp, &self.out_dir.join(&file_name))?;
+
+        self.start_block();
+        self.markdown
+            .push_str(&format!("![{}]({})\n", name.replace('_', " "), file_name));
+        Ok(())
+    }
+
+    fn render_figure(&self, page: &Page, region: &Region) -> Result<Bitmap> {
+        let scale = self.command.dpi as f32 / 72.;
+        let (mut width, mut height) = (
+            (region.bounds.width as f32 * scale).ceil().max(1.),
+            (region.bounds.height as f32 * scale).ceil().max(1.),
+        );
+        // Figures are written the way up they're displayed.
+        if page.rotation() % 2 == 1 {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let matrix = region_matrix(page, &region.bounds, (width, height), true)?;
+
+        let bmp = Bitmap::new(width as i32, height as i32, p::FPDFBitmap_BGR)?;
+        bmp.render_page_with_matrix(
+            page,
+            matrix,
+            width as i32,
+            height as i32,
+            Some(Color::WHITE.argb()),
+            0,
+        );
+        Ok(bmp)
+    }
+}
+
+// Escapes characters which Markdown would otherwise treat as formatting.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    // Text starting like a heading, quote, list item or rule.
+    if escaped.starts_with(['#', '>', '-', '+', '=']) {
+        escaped.insert(0, '\\');
+    } else if layout::list_marker(&escaped).is_some_and(|(ordered, _)| ordered) {
+        // eg. "1\. " rather than "1. ".
+        if let Some(pos) = escaped.find(['.', ')']) {
+            escaped.insert(pos, '\\');
+        }
+    }
+    escaped
+}
@@ -1,5 +1,7 @@
 use super::Command;
-use crate::bindings::Document;
+use crate::bindings::{Bitmap, Document, Page};
+use crate::encoder;
+use crate::syntax::ImageFormat;
 use anyhow::Result;
 use clap::Args;
 use std::fs;
@@ -8,22 +10,57 @@ use std::path::PathBuf;
 /// extract embedded images from a PDF
 #[derive(Args)]
 pub struct ExtractImagesCommand {
-    /// JPEG quality argument
+    /// JPEG, WebP and AVIF quality argument
     #[clap(long, default_value_t = 92)]
     quality: u8,
+    /// encode WebP images losslessly, ignoring --quality
+    #[clap(long)]
+    lossless: bool,
+    /// output image format - jpg, png, tiff, pnm, bmp, webp or avif
+    #[clap(long, default_value = "jpg")]
+    format: ImageFormat,
+    #[clap(flatten)]
+    size: ImageSizeArgs,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to a directory where the images will be written
+    out_dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct ImageSizeArgs {
     /// only extract images with a width >= min-width
     #[clap(long, default_value_t = 1)]
-    min_width: i32,
+    pub min_width: i32,
     /// only extract images with a height >= min-height
     #[clap(long, default_value_t = 1)]
-    min_height: i32,
+    pub min_height: i32,
     /// only extract images with an area >= min-area
     #[clap(long, default_value_t = 1)]
-    min_area: i32,
-    /// path to a PDF
-    pdf: PathBuf,
-    /// path to a directory where the images will be written
-    out_dir: PathBuf,
+    pub min_area: i32,
+}
+
+// Returns the bitmap of the image object at `obj_pos`, or None if the object isn't an image
+// or is smaller than `size` allows.
+pub fn load_image(
+    doc: &Document,
+    page: &Page,
+    obj_pos: usize,
+    size: &ImageSizeArgs,
+) -> Result<Option<Bitmap>> {
+    let obj = page.load_object(obj_pos)?;
+    let img_obj = match obj.into_image() {
+        Some(img_obj) => img_obj,
+        None => return Ok(None),
+    };
+    let bmp = img_obj.bitmap(doc, page)?;
+    if bmp.height() < size.min_height
+        || bmp.width() < size.min_width
+        || bmp.width() * bmp.height() < size.min_area
+    {
+        return Ok(None);
+    }
+    Ok(Some(bmp))
 }
 
 impl Command for ExtractImagesCommand {
@@ -32,6 +69,15 @@ impl Command for ExtractImagesCommand {
 
         fs::create_dir_all(&self.out_dir)?;
 
+        let encoder = encoder::new(
+            self.format,
+            &encoder::Options {
+                quality: self.quality,
+                lossless: self.lossless,
+                ..Default::default()
+            },
+        );
+
         let page_count = doc.page_count();
 
         let mut image_count = 0;
@@ -39,15 +85,7 @@ impl Command for ExtractImagesCommand {
             let page = doc.load_page(pos)?;
             let obj_count = page.object_count();
             for obj_pos in 0..obj_count {
-                let obj = page.load_object(obj_pos)?;
-                if let Some(img_obj) = obj.into_image() {
-                    let bmp = img_obj.bitmap(&doc, &page)?;
-                    if bmp.height() < self.min_height
-                        || bmp.width() < self.min_width
-                        || bmp.width() * bmp.height() < self.min_area
-                    {
-                        continue;
-                    }
+                if let Some(bmp) = load_image(&doc, &page, obj_pos, &self.size)? {
                     image_count += 1;
                     let image_path = self
                         .out_dir
@@ -55,8 +93,8 @@ impl Command for ExtractImagesCommand {
                             "{}_image_{image_count}",
                             self.pdf.file_stem().unwrap().to_str().unwrap(),
                         ))
-                        .with_extension("jpg");
-                    bmp.write_image(&image_path, self.quality)?;
+                        .with_extension(self.format.extension());
+                    encoder.encode(&bmp, &image_path)?;
                 }
             }
         }
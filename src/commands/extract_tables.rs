@@ -0,0 +1,113 @@
+use super::{resolve_labels, Command};
+use crate::bindings::Document;
+use crate::syntax::{DataFormat, PageSelection};
+use crate::tables::{self, Table};
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+/// extract tables from a PDF as CSV or JSON
+///
+/// Tables are found from their ruling lines or, for borderless tables, from the alignment of
+/// their columns.
+#[derive(Args)]
+pub struct ExtractTablesCommand {
+    /// pages to extract tables from
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// output format: csv or json
+    #[clap(long, default_value = "csv")]
+    format: DataFormat,
+    /// write a file per table to this directory instead of writing to stdout
+    #[clap(long)]
+    out_dir: Option<PathBuf>,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct PageTable {
+    page: usize,
+    #[serde(flatten)]
+    table: Table,
+}
+
+impl Command for ExtractTablesCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        if let Some(out_dir) = self.out_dir.as_ref() {
+            fs::create_dir_all(out_dir)?;
+        }
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut page_tables = vec![];
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let text_page = page.load_text_page()?;
+            for table in tables::find_tables(&page, &text_page) {
+                page_tables.push(PageTable { page: pos, table });
+            }
+        }
+
+        if let Some(out_dir) = self.out_dir.as_ref() {
+            // Tables are numbered from 1 on each page.
+            let (mut page, mut n) = (0, 0);
+            for page_table in page_tables.iter() {
+                if page_table.page != page {
+                    page = page_table.page;
+                    n = 0;
+                }
+                n += 1;
+                let path = out_dir
+                    .join(format!(
+                        "{}_{}_{}",
+                        self.pdf.file_stem().unwrap().to_str().unwrap(),
+                        page_table.page,
+                        n
+                    ))
+                    .with_extension(match self.format {
+                        DataFormat::Csv => "csv",
+                        DataFormat::Json => "json",
+                    });
+                match self.format {
+                    DataFormat::Csv => write_csv(File::create(path)?, &page_table.table)?,
+                    DataFormat::Json => fs::write(path, serde_json::to_string_pretty(page_table)?)?,
+                }
+            }
+        } else {
+            match self.format {
+                DataFormat::Csv => {
+                    // Tables are separated by blank lines.
+                    for (i, page_table) in page_tables.iter().enumerate() {
+                        if i > 0 {
+                            println!();
+                        }
+                        write_csv(io::stdout(), &page_table.table)?;
+                    }
+                }
+                DataFormat::Json => println!("{}", serde_json::to_string_pretty(&page_tables)?),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_csv(w: impl io::Write, table: &Table) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(w);
+    for row in table.rows.iter() {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
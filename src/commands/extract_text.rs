@@ -0,0 +1,122 @@
+use super::{resolve_labels, Command};
+use crate::bindings::Document;
+use crate::syntax::{PageSelection, TextFormat};
+use crate::text::{self, Line};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+
+/// extract text from a PDF
+#[derive(Args)]
+pub struct ExtractTextCommand {
+    /// pages to extract text from
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// output format: text, or json with the lines, words and characters of each page along
+    /// with their bounding boxes in PDF points
+    #[clap(long, default_value = "text")]
+    format: TextFormat,
+    /// rebuild lines and columns from the character positions and write the text in reading
+    /// order, roughly preserving its spacing
+    #[clap(long)]
+    layout: bool,
+    /// join words hyphenated across line breaks when using --layout
+    #[clap(long, requires = "layout")]
+    dehyphenate: bool,
+    /// write a file per page to this directory instead of writing to stdout
+    #[clap(long)]
+    out_dir: Option<PathBuf>,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct PageText {
+    page: usize,
+    // In points, before rotation - ie. the same space as the boxes of the lines.
+    width: f32,
+    height: f32,
+    // Degrees clockwise.
+    rotation: i32,
+    lines: Vec<Line>,
+}
+
+impl Command for ExtractTextCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        if self.layout && self.format != TextFormat::Text {
+            return Err(anyhow!("--layout only applies to text output"));
+        }
+
+        if let Some(out_dir) = self.out_dir.as_ref() {
+            fs::create_dir_all(out_dir)?;
+        }
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut stdout = io::stdout();
+        let mut json_pages = vec![];
+        for (n, pos) in pages.iter(page_count).enumerate() {
+            let page = doc.load_page(pos - 1)?;
+            let text_page = page.load_text_page()?;
+            let (text, extension) = match self.format {
+                TextFormat::Text if self.layout => {
+                    (text::layout(&text_page, self.dehyphenate), "txt")
+                }
+                TextFormat::Text => (text_page.text().replace("\r\n", "\n"), "txt"),
+                TextFormat::Json => {
+                    // PDFium reports the displayed size.
+                    let (mut width, mut height) = (page.width(), page.height());
+                    if page.rotation() % 2 == 1 {
+                        mem::swap(&mut width, &mut height);
+                    }
+                    let page_text = PageText {
+                        page: pos,
+                        width,
+                        height,
+                        rotation: page.rotation() * 90,
+                        lines: text::lines(&text_page),
+                    };
+                    if self.out_dir.is_none() {
+                        json_pages.push(page_text);
+                        continue;
+                    }
+                    (serde_json::to_string_pretty(&page_text)?, "json")
+                }
+            };
+
+            if let Some(out_dir) = self.out_dir.as_ref() {
+                let text_path = out_dir
+                    .join(format!(
+                        "{}_{}",
+                        self.pdf.file_stem().unwrap().to_str().unwrap(),
+                        pos
+                    ))
+                    .with_extension(extension);
+                fs::write(text_path, text)?;
+            } else {
+                // Pages are separated by form feeds like `pdftotext`.
+                if n > 0 {
+                    write!(stdout, "\x0c")?;
+                }
+                writeln!(stdout, "{}", text)?;
+            }
+        }
+
+        if self.format == TextFormat::Json && self.out_dir.is_none() {
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&json_pages)?)?;
+        }
+
+        Ok(())
+    }
+}
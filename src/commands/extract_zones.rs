@@ -0,0 +1,150 @@
+use super::Command;
+use crate::bindings::{Document, Page, TextPage};
+use crate::syntax::{DataFormat, Geometry};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// extract the text inside named zones of fixed-layout PDFs
+///
+/// The template is a JSON object mapping zone names to a page and a placement, eg.
+/// {"invoice_no": {"page": 1, "placement": "120x20+400+700"}}. Placements are in points
+/// from the bottom left of the page, either as a WxH+X+Y string or as an object.
+#[derive(Args)]
+pub struct ExtractZonesCommand {
+    /// output format: json or csv, with a column per zone
+    #[clap(long, default_value = "json")]
+    format: DataFormat,
+    /// path to a JSON template of zones
+    template: PathBuf,
+    /// paths to PDFs
+    #[clap(required = true)]
+    pdfs: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+pub struct Zone {
+    pub page: usize,
+    pub placement: Placement,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Placement {
+    Spec(String),
+    Geometry(Geometry),
+}
+
+impl Placement {
+    fn geometry(&self) -> Result<Geometry> {
+        match self {
+            Self::Spec(s) => s.parse(),
+            Self::Geometry(geom) => Ok(*geom),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record {
+    file: PathBuf,
+    fields: BTreeMap<String, String>,
+}
+
+impl Command for ExtractZonesCommand {
+    fn execute(self) -> Result<()> {
+        let json = fs::read_to_string(&self.template)?;
+        let template: BTreeMap<String, Zone> = serde_json::from_str(&json)?;
+        let zones = template
+            .iter()
+            .map(|(name, zone)| {
+                if zone.page == 0 {
+                    return Err(anyhow!("zone {}: pages start at 1", name));
+                }
+                Ok((name.as_str(), zone.page, zone.placement.geometry()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut records = vec![];
+        let mut failed = 0;
+        for pdf in self.pdfs.iter() {
+            match extract_zones(pdf, &zones) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    eprintln!("{}: {}", pdf.display(), e);
+                    failed += 1;
+                }
+            }
+        }
+
+        match self.format {
+            DataFormat::Json => println!("{}", serde_json::to_string_pretty(&records)?),
+            DataFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(io::stdout());
+                writer.write_record(
+                    Some("file")
+                        .into_iter()
+                        .chain(zones.iter().map(|zone| zone.0)),
+                )?;
+                for record in records.iter() {
+                    writer.write_record(
+                        Some(record.file.to_string_lossy().as_ref())
+                            .into_iter()
+                            .chain(record.fields.values().map(String::as_str)),
+                    )?;
+                }
+                writer.flush()?;
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow!(
+                "failed to extract {} of {} files",
+                failed,
+                self.pdfs.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn extract_zones(pdf: &Path, zones: &[(&str, usize, Geometry)]) -> Result<Record> {
+    let doc = Document::load(pdf)?;
+    // Zones often share a page so each is only loaded once. Text pages come first so they're
+    // dropped before their page.
+    let mut pages: HashMap<usize, (TextPage, Page)> = HashMap::new();
+
+    let mut fields = BTreeMap::new();
+    for &(name, pos, geom) in zones {
+        if pos > doc.page_count() {
+            return Err(anyhow!(
+                "zone {} is on page {} of {}",
+                name,
+                pos,
+                doc.page_count()
+            ));
+        }
+        let (text_page, _) = match pages.entry(pos) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let page = doc.load_page(pos - 1)?;
+                entry.insert((page.load_text_page()?, page))
+            }
+        };
+        let text = text_page.bounded_text(geom.x, geom.top(), geom.right(), geom.y);
+        fields.insert(
+            name.to_owned(),
+            text.replace("\r\n", "\n").trim().to_owned(),
+        );
+    }
+
+    Ok(Record {
+        file: pdf.to_owned(),
+        fields,
+    })
+}
@@ -0,0 +1,174 @@
+use super::Command;
+use crate::bindings::{Document, Font, Object};
+use crate::text;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// list the fonts used by the text of a PDF as JSON
+#[derive(Args)]
+pub struct FontsCommand {
+    /// write the programs of embedded fonts to this directory
+    #[clap(long)]
+    extract: Option<PathBuf>,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct FontInfo {
+    base_name: String,
+    family: String,
+    // Subset fonts only contain the glyphs the document uses.
+    subset: bool,
+    weight: Option<i32>,
+    // In degrees, negative for fonts which slant right.
+    italic_angle: Option<i32>,
+    embedded: bool,
+    pages: Vec<usize>,
+    // The name of the extracted font program, relative to the --extract directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+}
+
+impl Command for FontsCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        if let Some(dir) = self.extract.as_ref() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // PDFium may reload a font for each page so fonts are told apart by name.
+        let mut fonts: Vec<FontInfo> = vec![];
+        let mut font_pos: HashMap<(String, bool), usize> = HashMap::new();
+        let mut file_names = HashSet::new();
+        let mut failures = 0;
+
+        for pos in 0..doc.page_count() {
+            let page = doc.load_page(pos)?;
+            // A stack of the objects left to visit, in reverse order.
+            let mut objects: Vec<Object> = (0..page.object_count())
+                .rev()
+                .map(|obj_pos| page.load_object(obj_pos))
+                .collect::<Result<_, _>>()?;
+            while let Some(obj) = objects.pop() {
+                objects.extend(obj.form_objects().into_iter().rev());
+                let font = match obj.into_text() {
+                    Some(text_obj) => text_obj.font()?,
+                    None => continue,
+                };
+
+                let key = (font.base_name(), font.is_embedded());
+                if let Some(&i) = font_pos.get(&key) {
+                    if fonts[i].pages.last() != Some(&(pos + 1)) {
+                        fonts[i].pages.push(pos + 1);
+                    }
+                    continue;
+                }
+
+                let mut info = FontInfo {
+                    base_name: key.0.clone(),
+                    family: font.family_name(),
+                    subset: text::strip_subset_tag(&key.0).is_some(),
+                    weight: font.weight(),
+                    italic_angle: font.italic_angle(),
+                    embedded: key.1,
+                    pages: vec![pos + 1],
+                    file: None,
+                };
+                if let Some(dir) = self.extract.as_ref().filter(|_| info.embedded) {
+                    match extract(&font, &info.base_name, &mut file_names) {
+                        Ok((file_name, data)) => {
+                            fs::write(dir.join(&file_name), data)?;
+                            info.file = Some(file_name);
+                        }
+                        Err(err) => {
+                            eprintln!("{}: {}", info.base_name, err);
+                            failures += 1;
+                        }
+                    }
+                }
+                font_pos.insert(key, fonts.len());
+                fonts.push(info);
+            }
+        }
+
+        println!("{}", serde_json::to_string_pretty(&fonts)?);
+
+        if failures > 0 {
+            let embedded = fonts.iter().filter(|font| font.embedded).count();
+            return Err(anyhow!(
+                "failed to extract {} of {} embedded fonts",
+                failures,
+                embedded
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Returns a file name which isn't in `file_names` yet, along with the font program.
+fn extract(
+    font: &Font,
+    base_name: &str,
+    file_names: &mut HashSet<String>,
+) -> Result<(String, Vec<u8>)> {
+    let data = font.data()?;
+
+    let stem: String = base_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '+' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = if stem.is_empty() { "font" } else { &stem };
+    let ext = extension(&data);
+    let mut file_name = format!("{}.{}", stem, ext);
+    let mut n = 1;
+    while !file_names.insert(file_name.clone()) {
+        n += 1;
+        file_name = format!("{}_{}.{}", stem, n, ext);
+    }
+    Ok((file_name, data))
+}
+
+// Guesses the format of a font program from its first bytes.
+fn extension(data: &[u8]) -> &'static str {
+    match data {
+        [b'O', b'T', b'T', b'O', ..] => "otf",
+        [0, 1, 0, 0, ..] | [b't', b'r', b'u', b'e', ..] => "ttf",
+        [b't', b't', b'c', b'f', ..] => "ttc",
+        [0x80, 0x01, ..] => "pfb",
+        [b'%', b'!', ..] => "pfa",
+        // Bare CFF, as embedded for Type1C and CIDFontType0C fonts.
+        [1, 0, ..] => "cff",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(extension(b"OTTO\0\x0a"), "otf");
+        assert_eq!(extension(&[0, 1, 0, 0, 0, 0x0a]), "ttf");
+        assert_eq!(extension(b"true\0\x0a"), "ttf");
+        assert_eq!(extension(b"ttcf\0\x01"), "ttc");
+        assert_eq!(extension(&[0x80, 0x01, 0x10, 0x00]), "pfb");
+        assert_eq!(extension(b"%!PS-AdobeFont-1.0"), "pfa");
+        assert_eq!(extension(&[1, 0, 4, 2]), "cff");
+        assert_eq!(extension(b"wOFF"), "bin");
+        assert_eq!(extension(&[]), "bin");
+    }
+}
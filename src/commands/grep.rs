@@ -0,0 +1,153 @@
+use super::{resolve_labels, Command};
+use crate::bindings::Document;
+use crate::syntax::PageSelection;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// search the text of PDFs for a regular expression
+///
+/// Each page's lines are separated by "\n" so patterns like `\s+` match across line breaks.
+#[derive(Args)]
+pub struct GrepCommand {
+    /// pages to search in each PDF
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// match case-insensitively
+    #[clap(short, long)]
+    ignore_case: bool,
+    /// only print the paths of PDFs with at least one match - with --json, along with their
+    /// number of matches
+    #[clap(short = 'l', long)]
+    files_with_matches: bool,
+    /// only print the number of matches in each PDF
+    #[clap(short, long)]
+    count: bool,
+    /// print the results as JSON
+    #[clap(long)]
+    json: bool,
+    /// a regular expression, see https://docs.rs/regex for the syntax
+    pattern: String,
+    /// paths to PDFs
+    #[clap(required = true)]
+    pdfs: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct FileMatches {
+    file: PathBuf,
+    count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matches: Vec<Match>,
+}
+
+#[derive(Serialize)]
+struct Match {
+    page: usize,
+    text: String,
+    // The lines the match spans, joined by spaces.
+    context: String,
+}
+
+impl Command for GrepCommand {
+    fn execute(self) -> Result<()> {
+        let re = RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.ignore_case)
+            .multi_line(true)
+            .build()?;
+
+        let mut stdout = io::stdout();
+        let mut results = vec![];
+        let mut failed = 0;
+        for pdf in self.pdfs.iter() {
+            let file_matches = match self.grep_file(&re, pdf) {
+                Ok(file_matches) => file_matches,
+                Err(e) => {
+                    // Keep going so one broken file doesn't stop a search over thousands.
+                    eprintln!("{}: {}", pdf.display(), e);
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            if self.json {
+                if file_matches.count > 0 || self.count {
+                    results.push(file_matches);
+                }
+            } else if self.count {
+                writeln!(stdout, "{}:{}", pdf.display(), file_matches.count)?;
+            } else if self.files_with_matches {
+                if file_matches.count > 0 {
+                    writeln!(stdout, "{}", pdf.display())?;
+                }
+            } else {
+                for m in file_matches.matches.iter() {
+                    writeln!(stdout, "{}:{}: {}", pdf.display(), m.page, m.context)?;
+                }
+            }
+        }
+
+        if self.json {
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&results)?)?;
+        }
+
+        if failed > 0 {
+            return Err(anyhow!(
+                "failed to search {} of {} files",
+                failed,
+                self.pdfs.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl GrepCommand {
+    fn grep_file(&self, re: &Regex, pdf: &Path) -> Result<FileMatches> {
+        let doc = Document::load(pdf)?;
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut file_matches = FileMatches {
+            file: pdf.to_owned(),
+            count: 0,
+            matches: vec![],
+        };
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let text = page.load_text_page()?.text().replace("\r\n", "\n");
+
+            for m in re.find_iter(&text) {
+                file_matches.count += 1;
+                if self.files_with_matches && !self.count && !self.json {
+                    // One match is enough to list the file. JSON reports the real count.
+                    return Ok(file_matches);
+                }
+                if self.count || self.files_with_matches {
+                    continue;
+                }
+
+                let start = text[..m.start()].rfind('\n').map_or(0, |i| i + 1);
+                let end = text[m.end()..]
+                    .find('\n')
+                    .map_or(text.len(), |i| m.end() + i);
+                file_matches.matches.push(Match {
+                    page: pos,
+                    text: m.as_str().to_owned(),
+                    context: text[start..end].replace('\n', " ").trim().to_owned(),
+                });
+            }
+        }
+
+        Ok(file_matches)
+    }
+}
@@ -0,0 +1,186 @@
+use super::Command;
+use crate::bindings::Document;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// print information about a PDF as JSON
+#[derive(Args)]
+pub struct InfoCommand {
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Info {
+    // eg. "1.7"
+    version: Option<String>,
+    page_count: usize,
+    metadata: Metadata,
+    pages: Vec<PageInfo>,
+    permissions: Permissions,
+    // None if the document isn't encrypted.
+    security_handler_revision: Option<i32>,
+    file_ids: FileIds,
+    tagged: bool,
+}
+
+#[derive(Serialize)]
+struct Metadata {
+    title: Option<String>,
+    author: Option<String>,
+    subject: Option<String>,
+    keywords: Option<String>,
+    creator: Option<String>,
+    producer: Option<String>,
+    // Dates are converted to RFC 3339 where possible.
+    creation_date: Option<String>,
+    mod_date: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PageInfo {
+    page: usize,
+    // The page number as printed, eg. "iv".
+    label: Option<String>,
+    // In points, as displayed - ie. after rotation.
+    width: f32,
+    height: f32,
+    // Degrees clockwise.
+    rotation: i32,
+}
+
+#[derive(Serialize)]
+struct Permissions {
+    flags: u32,
+    print: bool,
+    modify: bool,
+    copy: bool,
+    annotate: bool,
+    fill_forms: bool,
+    extract_for_accessibility: bool,
+    assemble: bool,
+    print_high_quality: bool,
+}
+
+impl From<u32> for Permissions {
+    // See table 3.20 of the PDF reference.
+    fn from(flags: u32) -> Self {
+        let bit = |n: u32| flags & (1 << (n - 1)) != 0;
+        Self {
+            flags,
+            print: bit(3),
+            modify: bit(4),
+            copy: bit(5),
+            annotate: bit(6),
+            fill_forms: bit(9),
+            extract_for_accessibility: bit(10),
+            assemble: bit(11),
+            print_high_quality: bit(12),
+        }
+    }
+}
+
+// Hex encoded.
+#[derive(Serialize)]
+struct FileIds {
+    permanent: Option<String>,
+    changing: Option<String>,
+}
+
+impl Command for InfoCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let meta = |tag: &str| doc.meta_text(tag).filter(|text| !text.is_empty());
+        let date = |tag: &str| meta(tag).map(|date| to_rfc3339(&date).unwrap_or(date));
+
+        let mut pages = vec![];
+        for pos in 0..doc.page_count() {
+            let page = doc.load_page(pos)?;
+            pages.push(PageInfo {
+                page: pos + 1,
+                label: doc.page_label(pos),
+                width: page.width(),
+                height: page.height(),
+                rotation: page.rotation() * 90,
+            });
+        }
+
+        let info = Info {
+            version: doc
+                .file_version()
+                .map(|version| format!("{}.{}", version / 10, version % 10)),
+            page_count: doc.page_count(),
+            metadata: Metadata {
+                title: meta("Title"),
+                author: meta("Author"),
+                subject: meta("Subject"),
+                keywords: meta("Keywords"),
+                creator: meta("Creator"),
+                producer: meta("Producer"),
+                creation_date: date("CreationDate"),
+                mod_date: date("ModDate"),
+            },
+            pages,
+            permissions: doc.permissions().into(),
+            security_handler_revision: doc.security_handler_revision(),
+            file_ids: FileIds {
+                permanent: doc.file_identifier(true).map(|id| hex(&id)),
+                changing: doc.file_identifier(false).map(|id| hex(&id)),
+            },
+            tagged: doc.is_tagged(),
+        };
+
+        println!("{}", serde_json::to_string_pretty(&info)?);
+
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Converts a PDF date such as "D:20230415103000+02'00'" to "2023-04-15T10:30:00+02:00".
+// Everything after the year is optional.
+fn to_rfc3339(date: &str) -> Option<String> {
+    let s = date.strip_prefix("D:").unwrap_or(date);
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if digits < 4 || digits % 2 != 0 || digits > 14 {
+        return None;
+    }
+    // Pad the missing fields with their defaults, eg. "2023" is "20230101000000".
+    let mut fields = s[..digits].to_string();
+    fields.push_str(&"0101000000"[digits - 4..]);
+    let f = |range: std::ops::Range<usize>| &fields[range];
+
+    let zone = match &s[digits..] {
+        "" | "Z" => "Z".to_string(),
+        tz => {
+            let sign = tz.chars().next()?;
+            if sign != '+' && sign != '-' {
+                return None;
+            }
+            let parts: Vec<&str> = tz[1..].split('\'').filter(|p| !p.is_empty()).collect();
+            let hours = parts.first()?;
+            let minutes = parts.get(1).unwrap_or(&"00");
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            format!("{}{}:{}", sign, hours, minutes)
+        }
+    };
+
+    Some(format!(
+        "{}-{}-{}T{}:{}:{}{}",
+        f(0..4),
+        f(4..6),
+        f(6..8),
+        f(8..10),
+        f(10..12),
+        f(12..14),
+        zone
+    ))
+}
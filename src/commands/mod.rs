@@ -1,19 +1,62 @@
+use crate::bindings::Document;
+use crate::syntax::{Intervals, PageSelection};
+
 mod add_image;
 mod add_text;
+mod analyze_layout;
+mod attachments;
 mod create;
+mod diff;
 mod edit;
+mod export_html;
+mod export_markdown;
 mod extract_images;
+mod extract_tables;
+mod extract_text;
+mod extract_zones;
+mod fonts;
+mod grep;
+mod info;
+mod montage;
+mod outline;
 mod page_count;
 mod render;
+mod search;
 
 pub use add_image::*;
 pub use add_text::*;
+pub use analyze_layout::*;
+pub use attachments::*;
 pub use create::*;
+pub use diff::*;
 pub use edit::*;
+pub use export_html::*;
+pub use export_markdown::*;
 pub use extract_images::*;
+pub use extract_tables::*;
+pub use extract_text::*;
+pub use extract_zones::*;
+pub use fonts::*;
+pub use grep::*;
+pub use info::*;
+pub use montage::*;
+pub use outline::*;
 pub use page_count::*;
 pub use render::*;
+pub use search::*;
 
 pub trait Command {
     fn execute(self) -> anyhow::Result<()>;
 }
+
+// Resolves `pages` to page numbers, looking up any page labels in `doc`.
+fn resolve_labels(doc: &Document, pages: &PageSelection) -> anyhow::Result<Intervals> {
+    let labels: Vec<Option<String>> = if pages.has_labels() {
+        (0..doc.page_count())
+            .map(|pos| doc.page_label(pos))
+            .collect()
+    } else {
+        vec![]
+    };
+    pages.resolve_labels(&labels)
+}
@@ -0,0 +1,264 @@
+use super::{resolve_labels, Command};
+use crate::bindings::{Bitmap, Document};
+use crate::encoder;
+use crate::syntax::{Color, ImageFormat, PageSelection, Size};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use pdfium_sys as p;
+use std::path::PathBuf;
+
+/// render pages as thumbnails laid out in a grid
+#[derive(Args)]
+pub struct MontageCommand {
+    /// pages to include
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// number of thumbnails per row
+    #[clap(long, default_value_t = 4)]
+    columns: u32,
+    /// thumbnail width in pixels - reduced if needed to fit max-size
+    #[clap(long, default_value_t = 200)]
+    thumbnail_width: u32,
+    /// space between thumbnails in pixels
+    #[clap(long, default_value_t = 10)]
+    spacing: u32,
+    /// caption each thumbnail with its page number
+    #[clap(long)]
+    captions: bool,
+    /// maximum output image size - eg. 2000x2000
+    #[clap(long)]
+    max_size: Option<Size>,
+    /// background color - eg. ffffff or ffffff80
+    #[clap(long, default_value = "ffffff")]
+    background: Color,
+    /// JPEG, WebP and AVIF quality argument
+    #[clap(long, default_value_t = 92)]
+    quality: u8,
+    /// path to a PDF
+    pdf: PathBuf,
+    /// path to write the resulting image - the format is based on the extension
+    out: PathBuf,
+}
+
+struct Layout {
+    thumbnail_width: u32,
+    // Thumbnail heights are scaled to the page's aspect ratio.
+    thumbnail_heights: Vec<u32>,
+    caption_scale: u32,
+}
+
+impl Layout {
+    fn caption_height(&self, spacing: u32) -> u32 {
+        if self.caption_scale > 0 {
+            GLYPH_HEIGHT * self.caption_scale + spacing
+        } else {
+            0
+        }
+    }
+
+    fn row_heights(&self, columns: u32) -> Vec<u32> {
+        self.thumbnail_heights
+            .chunks(columns as usize)
+            .map(|row| row.iter().copied().max().unwrap_or(0))
+            .collect()
+    }
+
+    fn size(&self, columns: u32, spacing: u32) -> (u32, u32) {
+        let columns_used = columns.min(self.thumbnail_heights.len() as u32);
+        let width = columns_used * (self.thumbnail_width + spacing) + spacing;
+        let height = self
+            .row_heights(columns)
+            .iter()
+            .map(|height| height + self.caption_height(spacing) + spacing)
+            .sum::<u32>()
+            + spacing;
+        (width, height)
+    }
+}
+
+impl Command for MontageCommand {
+    fn execute(self) -> Result<()> {
+        if self.columns == 0 {
+            return Err(anyhow!("columns must be greater than zero"));
+        }
+        let format: ImageFormat = self
+            .out
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or_else(|| anyhow!("output path is missing an extension"))?
+            .parse()?;
+
+        let doc = Document::load(&self.pdf)?;
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+        let pages: Vec<usize> = pages.iter(page_count).collect();
+        if pages.is_empty() {
+            return Err(anyhow!("no pages selected"));
+        }
+
+        // Page width/height ratios.
+        let mut ratios = vec![];
+        for pos in pages.iter() {
+            let page = doc.load_page(pos - 1)?;
+            ratios.push(page.height() / page.width());
+        }
+        let layout = self.layout(&ratios)?;
+        let (width, height) = layout.size(self.columns, self.spacing);
+
+        let mut sheet = RgbaImage::from_pixel(width, height, rgba(self.background));
+        let caption_color =
+            if self.background.r as u32 + self.background.g as u32 + self.background.b as u32 > 384
+            {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            };
+
+        let row_heights = layout.row_heights(self.columns);
+        let mut y = self.spacing;
+        for (row, row_pages) in pages.chunks(self.columns as usize).enumerate() {
+            for (col, pos) in row_pages.iter().enumerate() {
+                let index = row * self.columns as usize + col;
+                let thumbnail_height = layout.thumbnail_heights[index];
+                let x = self.spacing + col as u32 * (layout.thumbnail_width + self.spacing);
+
+                let page = doc.load_page(pos - 1)?;
+                let bmp = Bitmap::new(
+                    layout.thumbnail_width as i32,
+                    thumbnail_height as i32,
+                    p::FPDFBitmap_BGRA,
+                )?;
+                bmp.render_page(
+                    &page,
+                    layout.thumbnail_width as i32,
+                    thumbnail_height as i32,
+                    0,
+                    Some(Color::WHITE.argb()),
+                    0,
+                );
+                let thumbnail = bmp.to_image()?.into_rgba8();
+                // Vertically align thumbnails to the bottom of the row, just above the captions.
+                let thumbnail_y = y + row_heights[row] - thumbnail_height;
+                imageops::overlay(&mut sheet, &thumbnail, x, thumbnail_y);
+
+                if layout.caption_scale > 0 {
+                    let caption = pos.to_string();
+                    let caption_width = caption_width(&caption, layout.caption_scale);
+                    draw_caption(
+                        &mut sheet,
+                        &caption,
+                        x + layout.thumbnail_width.saturating_sub(caption_width) / 2,
+                        y + row_heights[row] + self.spacing,
+                        layout.caption_scale,
+                        caption_color,
+                    );
+                }
+            }
+            y += row_heights[row] + layout.caption_height(self.spacing) + self.spacing;
+        }
+
+        let bmp = Bitmap::new_with_image(DynamicImage::ImageRgba8(sheet))?;
+        let options = encoder::Options {
+            quality: self.quality,
+            ..Default::default()
+        };
+        encoder::new(format, &options).encode(&bmp, &self.out)?;
+
+        Ok(())
+    }
+}
+
+impl MontageCommand {
+    // Shrinks the thumbnails until the sheet fits within `max_size`.
+    fn layout(&self, ratios: &[f32]) -> Result<Layout> {
+        let mut thumbnail_width = self.thumbnail_width;
+        loop {
+            let layout = Layout {
+                thumbnail_width,
+                thumbnail_heights: ratios
+                    .iter()
+                    .map(|ratio| ((thumbnail_width as f32 * ratio).round() as u32).max(1))
+                    .collect(),
+                caption_scale: if self.captions {
+                    (thumbnail_width / 100).max(1)
+                } else {
+                    0
+                },
+            };
+            let (width, height) = layout.size(self.columns, self.spacing);
+            let max_width = self.max_size.as_ref().and_then(|size| size.width);
+            let max_height = self.max_size.as_ref().and_then(|size| size.height);
+            let scale = max_width
+                .map_or(1., |max| max / width as f32)
+                .min(max_height.map_or(1., |max| max / height as f32));
+            if scale >= 1. {
+                return Ok(layout);
+            }
+            if thumbnail_width <= 1 {
+                return Err(anyhow!("thumbnails don't fit within the max size"));
+            }
+            thumbnail_width = ((thumbnail_width as f32 * scale).floor() as u32)
+                .min(thumbnail_width - 1)
+                .max(1);
+        }
+    }
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    Rgba([color.r, color.g, color.b, color.a])
+}
+
+fn caption_width(caption: &str, scale: u32) -> u32 {
+    let len = caption.len() as u32;
+    (len * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+fn draw_caption(img: &mut RgbaImage, caption: &str, x: u32, y: u32, scale: u32, color: Rgba<u8>) {
+    for (pos, c) in caption.chars().enumerate() {
+        let glyph = match c.to_digit(10) {
+            Some(digit) => DIGITS[digit as usize],
+            None => continue,
+        };
+        let glyph_x = x + pos as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let px = glyph_x + col * scale + dx;
+                        let py = y + row as u32 * scale + dy;
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+// A 5x7 bitmap font for page numbers - one byte per row, using the low 5 bits.
+#[rustfmt::skip]
+const DIGITS: [[u8; 7]; 10] = [
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+];
@@ -0,0 +1,87 @@
+use super::Command;
+use crate::bindings::{Bookmark, Document};
+use crate::syntax::TextFormat;
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// print the outline (bookmarks) of a PDF
+#[derive(Args)]
+pub struct OutlineCommand {
+    /// output format: text, indented by nesting level, or json with the page and position
+    /// each bookmark points to
+    #[clap(long, default_value = "text")]
+    format: TextFormat,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    title: String,
+    // From 1, or None if the bookmark doesn't point to a page of the document.
+    page: Option<usize>,
+    // In points from the bottom left of the page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zoom: Option<f32>,
+    children: Vec<Entry>,
+}
+
+impl Command for OutlineCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let mut seen = HashSet::new();
+        let entries = read_entries(&doc, None, &mut seen);
+
+        match self.format {
+            TextFormat::Text => print_entries(&entries, 0),
+            TextFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+        }
+
+        Ok(())
+    }
+}
+
+// Broken outlines can link back to earlier bookmarks so each one is only visited once.
+fn read_entries(
+    doc: &Document,
+    parent: Option<&Bookmark>,
+    seen: &mut HashSet<Bookmark>,
+) -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut next = doc.first_bookmark(parent);
+    while let Some(bookmark) = next {
+        if !seen.insert(bookmark) {
+            break;
+        }
+        next = doc.next_bookmark(&bookmark);
+        let dest = bookmark.destination(doc);
+        let entry = Entry {
+            title: bookmark.title(),
+            page: dest.as_ref().map(|dest| dest.page + 1),
+            x: dest.as_ref().and_then(|dest| dest.x),
+            y: dest.as_ref().and_then(|dest| dest.y),
+            zoom: dest.as_ref().and_then(|dest| dest.zoom),
+            children: read_entries(doc, Some(&bookmark), seen),
+        };
+        entries.push(entry);
+    }
+    entries
+}
+
+fn print_entries(entries: &[Entry], depth: usize) {
+    for entry in entries {
+        match entry.page {
+            Some(page) => println!("{}{} ({})", "  ".repeat(depth), entry.title, page),
+            None => println!("{}{}", "  ".repeat(depth), entry.title),
+        }
+        print_entries(&entry.children, depth + 1);
+    }
+}
@@ -1,13 +1,26 @@
-use super::Command;
-use crate::bindings::{Bitmap, Document};
-use crate::syntax::{Intervals, Size};
-use anyhow::Result;
+use super::{resolve_labels, Command};
+use crate::bindings::{Bitmap, Document, FormFillEnvironment, Page};
+use crate::encoder::{
+    self,
+    tiff::{TiffOptions, TiffWriter},
+    Encoder,
+};
+use crate::syntax::{
+    concat, Color, ColorMode, Geometry, ImageFormat, Intervals, PageSelection, Size,
+    TiffCompression, TileLayout, TimeoutAction,
+};
+use anyhow::{anyhow, Result};
 use clap::Args;
 use pdfium_sys as p;
-use std::path::PathBuf;
-use std::{fs, mem};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::time::{Duration, Instant};
+use std::{env, fs, mem, thread};
 
-/// render PDF pages to JPEG images
+/// render PDF pages to images
 #[derive(Args)]
 pub struct RenderCommand {
     /// rotation is ignored by default - use this flag to respect rotation
@@ -15,16 +28,85 @@ pub struct RenderCommand {
     rotate: bool,
     /// pages to render
     #[clap(long)]
-    pages: Option<Intervals>,
+    pages: Option<PageSelection>,
     /// output image size
     #[clap(long)]
     size: Option<Size>,
+    /// only render this area of each page in points, measured in page space like the boxes of
+    /// extract-text - eg. 200x100+50+50
+    #[clap(long)]
+    region: Option<Geometry>,
     /// dots/pixels per inch - only relevant if size is unspecified
     #[clap(long, default_value_t = 300)]
     dpi: u32,
-    /// JPEG quality argument
+    /// JPEG, WebP and AVIF quality argument
     #[clap(long, default_value_t = 92)]
     quality: u8,
+    /// encode WebP images losslessly, ignoring --quality
+    #[clap(long)]
+    lossless: bool,
+    /// output image format - jpg, png, tiff, pnm, bmp, webp or avif
+    #[clap(long, default_value = "jpg")]
+    format: ImageFormat,
+    /// background color - eg. ffffff or ffffff80 (png/tiff/bmp default to transparent)
+    #[clap(long)]
+    background: Option<Color>,
+    /// write all pages to a single multi-page TIFF instead of one image per page
+    #[clap(long)]
+    multipage: bool,
+    /// TIFF color mode - color, gray or bilevel
+    #[clap(long, default_value = "color")]
+    color_mode: ColorMode,
+    /// TIFF compression - none or g4 (g4 requires the bilevel color mode)
+    #[clap(long, default_value = "none")]
+    compression: TiffCompression,
+    /// gray level (0-255) below which pixels become black in bilevel TIFFs
+    #[clap(long, default_value_t = 128)]
+    threshold: u8,
+    /// dither instead of thresholding bilevel TIFFs
+    #[clap(long)]
+    dither: bool,
+    /// render annotations
+    #[clap(long)]
+    annotations: bool,
+    /// render form fields and their values (implies --annotations)
+    #[clap(long)]
+    forms: bool,
+    /// render as if printing - eg. hides annotations flagged as no-print
+    #[clap(long)]
+    printing: bool,
+    /// optimize text rendering for LCD displays
+    #[clap(long)]
+    lcd_text: bool,
+    /// render in grayscale
+    #[clap(long)]
+    grayscale: bool,
+    /// give up on pages which take longer than this many seconds to render
+    #[clap(long)]
+    page_timeout: Option<f64>,
+    /// what to do with pages that time out - skip or draft (retry at a lower quality)
+    #[clap(long, default_value = "skip")]
+    on_timeout: TimeoutAction,
+    /// write a tile pyramid for each page instead of a single image - dzi or xyz
+    #[clap(long)]
+    tiles: Option<TileLayout>,
+    /// tile width/height in pixels
+    #[clap(long, default_value_t = 256)]
+    tile_size: u32,
+    /// pixels of overlap between neighbouring DZI tiles
+    #[clap(long, default_value_t = 0)]
+    tile_overlap: u32,
+    /// name images by page label (eg. cover, iv, 12) rather than page number - pages without
+    /// a label keep their number and the number is appended to labels shared by several pages
+    #[clap(long)]
+    label_names: bool,
+    /// number of worker processes to split the pages across
+    #[clap(long, default_value_t = 1)]
+    jobs: usize,
+    /// render these pages instead of --pages, reporting each one and any error on stdout - used
+    /// by worker processes
+    #[clap(long, hide = true)]
+    worker: Option<Intervals>,
     /// path to a PDF
     pdf: PathBuf,
     /// path to a directory where the images will be written
@@ -33,76 +115,613 @@ pub struct RenderCommand {
 
 impl Command for RenderCommand {
     fn execute(self) -> Result<()> {
-        let doc = Document::load(&self.pdf)?;
+        if self.worker.is_none() {
+            return self.run();
+        }
+        // Workers report their error on stdout, where the parent reads it, rather than leaving it
+        // to `main`.
+        if let Err(err) = self.run() {
+            let message = format!("{:#}", err).replace('\n', " ");
+            println!("error {}", message);
+            process::exit(1);
+        }
+        Ok(())
+    }
+}
 
-        fs::create_dir_all(&self.out_dir)?;
+impl RenderCommand {
+    fn run(&self) -> Result<()> {
+        if let Some(region) = self.region.as_ref() {
+            if region.width <= 0. || region.height <= 0. {
+                return Err(anyhow!("region must have a non-zero width and height"));
+            }
+        }
+        // Regions and tiles are rendered with a matrix which PDFium only supports for plain
+        // rendering.
+        if self.region.is_some() || self.tiles.is_some() {
+            if self.forms {
+                return Err(anyhow!(
+                    "form fields can't be rendered with a region or tiles"
+                ));
+            }
+            if self.page_timeout.is_some() {
+                return Err(anyhow!(
+                    "page timeouts aren't supported with a region or tiles"
+                ));
+            }
+        }
+        if self.tiles.is_some() && self.multipage {
+            return Err(anyhow!("tiles can't be written to a multipage TIFF"));
+        }
+        if self.multipage && self.format != ImageFormat::Tiff {
+            return Err(anyhow!("multipage output requires the tiff format"));
+        }
+        if self.tile_size == 0 {
+            return Err(anyhow!("tile size must be greater than zero"));
+        }
+        if let Some(timeout) = self.page_timeout {
+            // Also rules out NaN, infinite and overflowing durations.
+            if timeout <= 0. || Duration::try_from_secs_f64(timeout).is_err() {
+                return Err(anyhow!(
+                    "page timeout must be a number of seconds greater than zero"
+                ));
+            }
+        }
+
+        let options = encoder::Options {
+            quality: self.quality,
+            lossless: self.lossless,
+            tiff: TiffOptions {
+                color_mode: self.color_mode,
+                compression: self.compression,
+                threshold: self.threshold,
+                dither: self.dither,
+                dpi: if self.size.is_none() {
+                    Some(self.dpi)
+                } else {
+                    None
+                },
+            },
+        };
+        options.tiff.validate()?;
+
+        let doc = Document::load(&self.pdf)?;
 
         let page_count = doc.page_count();
-        let pages = if let Some(pages) = self.pages.as_ref() {
+        let pages = if let Some(pages) = self.worker.as_ref() {
             pages.clone()
+        } else if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
         } else {
             (1..=page_count).into()
         };
 
+        // Forms are set up before splitting the pages so that a failure is reported once, not
+        // by every worker.
+        let form = if self.forms {
+            Some(doc.init_form_fill_environment()?)
+        } else {
+            None
+        };
+
+        if self.jobs > 1 && self.worker.is_none() {
+            if self.multipage {
+                return Err(anyhow!("multipage output can't be split across jobs"));
+            }
+            fs::create_dir_all(&self.out_dir)?;
+            return run_jobs(self.jobs, pages.iter(page_count).collect());
+        }
+
+        let flags = self.flags();
+
+        fs::create_dir_all(&self.out_dir)?;
+
+        let encoder = encoder::new(self.format, &options);
+        let file_stem = self.pdf.file_stem().unwrap().to_str().unwrap();
+        let page_names = self.page_names(&doc);
+
+        let mut multipage = if self.multipage {
+            let path = self.out_dir.join(file_stem).with_extension("tiff");
+            Some(TiffWriter::create(path, options.tiff)?)
+        } else {
+            None
+        };
+
         for pos in pages.iter(page_count) {
             let page = doc.load_page(pos - 1)?;
 
-            let (mut width, mut height) = (page.width(), page.height());
-            let rotation = if !self.rotate {
-                let rotation = page.rotation();
-                if rotation == 1 || rotation == 3 {
-                    mem::swap(&mut width, &mut height);
+            let name = format!("{}_{}", file_stem, page_names[pos - 1]);
+
+            if let Some(layout) = self.tiles {
+                self.render_tiles(&page, layout, &name, encoder.as_ref(), flags)?;
+                if self.worker.is_some() {
+                    println!("{}", pos);
                 }
-                if rotation > 0 {
-                    4 - rotation
+                continue;
+            }
+
+            let bmp = match self.render_page(&page, form.as_ref(), flags)? {
+                Rendered::Done(bmp) => bmp,
+                Rendered::Draft(bmp) => {
+                    eprintln!("page {}: timed out - rendered a draft", pos);
+                    bmp
+                }
+                Rendered::TimedOut => {
+                    eprintln!("page {}: timed out - skipped", pos);
+                    if self.worker.is_some() {
+                        println!("{}", pos);
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(writer) = multipage.as_mut() {
+                writer.write_page(&bmp)?;
+                continue;
+            }
+
+            let image_path = self
+                .out_dir
+                .join(name)
+                .with_extension(self.format.extension());
+            encoder.encode(&bmp, &image_path)?;
+
+            if self.worker.is_some() {
+                println!("{}", pos);
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the name of each page's images - its number, or its label with --label-names.
+    // Labels are free text so anything which isn't safe in a file name is replaced, and since
+    // they needn't be unique, the page number is appended to any name shared by several pages.
+    // Names are chosen for the whole document so that workers agree on them.
+    fn page_names(&self, doc: &Document) -> Vec<String> {
+        let names: Vec<String> = (0..doc.page_count())
+            .map(
+                |pos| match doc.page_label(pos).filter(|_| self.label_names) {
+                    Some(label) => label
+                        .chars()
+                        .map(|c| {
+                            if c.is_alphanumeric() || c == '-' {
+                                c
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect(),
+                    None => (pos + 1).to_string(),
+                },
+            )
+            .collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for name in names.iter() {
+            *counts.entry(name).or_default() += 1;
+        }
+        names
+            .iter()
+            .enumerate()
+            .map(|(pos, name)| {
+                if counts[name.as_str()] > 1 {
+                    format!("{}_{}", name, pos + 1)
                 } else {
-                    0
+                    name.clone()
+                }
+            })
+            .collect()
+    }
+
+    fn render_page(
+        &self,
+        page: &Page,
+        form: Option<&FormFillEnvironment>,
+        flags: i32,
+    ) -> Result<Rendered> {
+        let (size, rotation) = self.output_size(page);
+        let width = size.0 as i32;
+        let height = size.1 as i32;
+        let new_bitmap = || {
+            let bmp_size = round_bmp_size(size);
+            let bmp_width = bmp_size.0 as i32;
+            let bmp_height = bmp_size.1 as i32;
+            Bitmap::new(bmp_width, bmp_height, self.bitmap_format())
+        };
+        let background = self.background().map(|c| c.argb());
+        let mut draft = false;
+        let bmp = if let Some(region) = self.region.as_ref() {
+            let bmp = new_bitmap()?;
+            let matrix = region_matrix(page, region, size, self.rotate)?;
+            bmp.render_page_with_matrix(page, matrix, width, height, background, flags);
+            bmp
+        } else if let Some(timeout) = self.page_timeout {
+            let timeout = Duration::from_secs_f64(timeout);
+            let bmp = new_bitmap()?;
+            let deadline = Instant::now() + timeout;
+            if bmp.render_page_progressive(
+                page, width, height, rotation, background, flags, deadline,
+            )? {
+                bmp
+            } else if self.on_timeout == TimeoutAction::Draft {
+                // Anti-aliasing is usually what makes pathological pages slow.
+                let draft_flags = flags
+                    | (p::FPDF_RENDER_NO_SMOOTHTEXT
+                        | p::FPDF_RENDER_NO_SMOOTHIMAGE
+                        | p::FPDF_RENDER_NO_SMOOTHPATH) as i32;
+                let bmp = new_bitmap()?;
+                let deadline = Instant::now() + timeout;
+                if !bmp.render_page_progressive(
+                    page,
+                    width,
+                    height,
+                    rotation,
+                    background,
+                    draft_flags,
+                    deadline,
+                )? {
+                    return Ok(Rendered::TimedOut);
                 }
+                draft = true;
+                bmp
+            } else {
+                return Ok(Rendered::TimedOut);
+            }
+        } else {
+            let bmp = new_bitmap()?;
+            bmp.render_page(page, width, height, rotation, background, flags);
+            bmp
+        };
+        if let Some(form) = form {
+            form.draw(&bmp, page, width, height, rotation, flags);
+        }
+
+        Ok(if draft {
+            Rendered::Draft(bmp)
+        } else {
+            Rendered::Done(bmp)
+        })
+    }
+
+    // Returns the size of the rendered image in pixels and the rotation to render with.
+    fn output_size(&self, page: &Page) -> ((f32, f32), i32) {
+        let (mut width, mut height) = (page.width(), page.height());
+        let rotation = if !self.rotate {
+            let rotation = page.rotation();
+            if rotation == 1 || rotation == 3 {
+                mem::swap(&mut width, &mut height);
+            }
+            if rotation > 0 {
+                4 - rotation
             } else {
                 0
-            };
-            let size = self.size.as_ref().map_or(
-                {
-                    // Width/height are in points.
-                    // 72 points per inch.
-                    let scaled_width = (width / 72. * self.dpi as f32).round();
-                    (scaled_width, (scaled_width / width * height).round())
-                },
-                |size| {
-                    let wh = if size.width.is_some() && size.height.is_some() {
-                        (size.width.unwrap(), size.height.unwrap())
-                    } else if let Some(size_width) = size.width {
-                        (size_width, height / width * size_width)
-                    } else if let Some(size_height) = size.height {
-                        (width / height * size_height, size_height)
-                    } else {
-                        (width, height)
-                    };
-                    (wh.0.round(), wh.1.round())
-                },
+            }
+        } else {
+            0
+        };
+        if let Some(region) = self.region.as_ref() {
+            // The region is in unrotated page space.
+            width = region.width as f32;
+            height = region.height as f32;
+            if self.rotate && page.rotation() % 2 == 1 {
+                mem::swap(&mut width, &mut height);
+            }
+        }
+        let size = self.size.as_ref().map_or(
+            {
+                // Width/height are in points.
+                // 72 points per inch.
+                let scaled_width = (width / 72. * self.dpi as f32).round();
+                (scaled_width, (scaled_width / width * height).round())
+            },
+            |size| {
+                let wh = if size.width.is_some() && size.height.is_some() {
+                    (size.width.unwrap(), size.height.unwrap())
+                } else if let Some(size_width) = size.width {
+                    (size_width, height / width * size_width)
+                } else if let Some(size_height) = size.height {
+                    (width / height * size_height, size_height)
+                } else {
+                    (width, height)
+                };
+                (wh.0.round(), wh.1.round())
+            },
+        );
+        (size, rotation)
+    }
+
+    // Writes a tile pyramid for the page (or region) - each zoom level halves the size of
+    // the next, down to a single pixel for DZI or a single tile for XYZ.
+    fn render_tiles(
+        &self,
+        page: &Page,
+        layout: TileLayout,
+        name: &str,
+        encoder: &dyn Encoder,
+        flags: i32,
+    ) -> Result<()> {
+        let area = match self.region {
+            Some(region) => region,
+            None => {
+                let (left, bottom, right, top) = page.bounding_box()?;
+                Geometry {
+                    width: (right - left) as f64,
+                    height: (top - bottom) as f64,
+                    x: left as f64,
+                    y: bottom as f64,
+                }
+            }
+        };
+        let (size, _) = self.output_size(page);
+        let tile_size = self.tile_size as f32;
+        let overlap = match layout {
+            TileLayout::Dzi => self.tile_overlap as f32,
+            TileLayout::Xyz => 0.,
+        };
+        let max_level = match layout {
+            TileLayout::Dzi => size.0.max(size.1).log2().ceil(),
+            TileLayout::Xyz => (size.0.max(size.1) / tile_size).log2().ceil().max(0.),
+        } as i32;
+        let background = self.background().map(|c| c.argb());
+        let ext = self.format.extension();
+
+        let dir = match layout {
+            TileLayout::Dzi => self.out_dir.join(format!("{}_files", name)),
+            TileLayout::Xyz => self.out_dir.join(name),
+        };
+        for level in 0..=max_level {
+            let scale = 0.5f32.powi(max_level - level);
+            let level_size = (
+                (size.0 * scale).ceil().max(1.),
+                (size.1 * scale).ceil().max(1.),
             );
-            let width = size.0 as i32;
-            let height = size.1 as i32;
-            let bmp = {
-                let bmp_size = round_bmp_size(size);
-                let bmp_width = bmp_size.0 as i32;
-                let bmp_height = bmp_size.1 as i32;
-                Bitmap::new(bmp_width, bmp_height, p::FPDFBitmap_BGR)?
-            };
-            bmp.render_page(&page, width, height, rotation);
+            let matrix = region_matrix(page, &area, level_size, self.rotate)?;
+            let cols = (level_size.0 / tile_size).ceil() as i32;
+            let rows = (level_size.1 / tile_size).ceil() as i32;
 
-            let image_path = self
-                .out_dir
-                .join(format!(
-                    "{}_{}",
-                    self.pdf.file_stem().unwrap().to_str().unwrap(),
-                    pos
-                ))
-                .with_extension("jpg");
-            bmp.write_image(&image_path, self.quality)?;
+            for col in 0..cols {
+                for row in 0..rows {
+                    let (x, width) = tile_span(col, tile_size, overlap, level_size.0);
+                    let (y, height) = tile_span(row, tile_size, overlap, level_size.1);
+                    let bmp = Bitmap::new(width as i32, height as i32, self.bitmap_format())?;
+                    bmp.render_page_with_matrix(
+                        page,
+                        concat(matrix, [1., 0., 0., 1., -x, -y]),
+                        width as i32,
+                        height as i32,
+                        background,
+                        flags,
+                    );
+
+                    let path = match layout {
+                        TileLayout::Dzi => dir
+                            .join(level.to_string())
+                            .join(format!("{}_{}.{}", col, row, ext)),
+                        TileLayout::Xyz => dir
+                            .join(level.to_string())
+                            .join(col.to_string())
+                            .join(format!("{}.{}", row, ext)),
+                    };
+                    fs::create_dir_all(path.parent().unwrap())?;
+                    encoder.encode(&bmp, &path)?;
+                }
+            }
+        }
+
+        match layout {
+            TileLayout::Dzi => {
+                let dzi = format!(
+                    concat!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                        "<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" ",
+                        "Format=\"{}\" Overlap=\"{}\" TileSize=\"{}\">\n",
+                        "  <Size Width=\"{}\" Height=\"{}\"/>\n",
+                        "</Image>\n"
+                    ),
+                    ext, overlap, tile_size, size.0, size.1
+                );
+                fs::write(self.out_dir.join(name).with_extension("dzi"), dzi)?;
+            }
+            TileLayout::Xyz => {
+                let manifest = serde_json::json!({
+                    "width": size.0,
+                    "height": size.1,
+                    "tile_size": tile_size,
+                    "min_zoom": 0,
+                    "max_zoom": max_level,
+                    "format": ext,
+                });
+                fs::write(
+                    dir.join("manifest.json"),
+                    serde_json::to_string_pretty(&manifest)?,
+                )?;
+            }
         }
+
         Ok(())
     }
+
+    fn bitmap_format(&self) -> u32 {
+        if self.format.supports_alpha() {
+            p::FPDFBitmap_BGRA
+        } else {
+            p::FPDFBitmap_BGR
+        }
+    }
+
+    fn background(&self) -> Option<Color> {
+        if self.format.supports_alpha() {
+            self.background
+        } else {
+            Some(self.background.unwrap_or(Color::WHITE))
+        }
+    }
+
+    fn flags(&self) -> i32 {
+        let mut flags = 0;
+        if self.annotations || self.forms {
+            flags |= p::FPDF_ANNOT;
+        }
+        if self.printing {
+            flags |= p::FPDF_PRINTING;
+        }
+        if self.lcd_text {
+            flags |= p::FPDF_LCD_TEXT;
+        }
+        if self.grayscale {
+            flags |= p::FPDF_GRAYSCALE;
+        }
+        flags as i32
+    }
+}
+
+enum Rendered {
+    Done(Bitmap),
+    // Rendered at a lower quality after timing out.
+    Draft(Bitmap),
+    TimedOut,
+}
+
+// PDFium isn't thread-safe so pages are split across worker processes instead. A worker which
+// fails (or crashes) is restarted on its remaining pages - skipping the page it failed on.
+fn run_jobs(jobs: usize, mut pages: Vec<usize>) -> Result<()> {
+    // Pages selected more than once could otherwise end up in two workers writing the same
+    // file at once.
+    let mut seen = HashSet::new();
+    pages.retain(|&pos| seen.insert(pos));
+
+    let exe = env::current_exe()?;
+    let chunk_size = pages.len().div_ceil(jobs).max(1);
+    let handles: Vec<_> = pages
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let exe = exe.clone();
+            let chunk = chunk.to_vec();
+            thread::spawn(move || run_worker(&exe, chunk))
+        })
+        .collect();
+
+    let mut failures = vec![];
+    for handle in handles {
+        let worker_failures = handle
+            .join()
+            .map_err(|_| anyhow!("a thread running a worker panicked"))??;
+        failures.extend(worker_failures);
+    }
+    failures.sort();
+    for (pos, err) in failures.iter() {
+        eprintln!("page {}: {}", pos, err);
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "failed to render {} of {} pages",
+            failures.len(),
+            pages.len()
+        ))
+    }
+}
+
+fn run_worker(exe: &Path, mut pages: Vec<usize>) -> Result<Vec<(usize, String)>> {
+    let mut failures = vec![];
+    while !pages.is_empty() {
+        // Workers write anything they report (eg. timeouts) straight to stderr, and the number
+        // of each page they finish followed by any error to stdout.
+        let mut child = process::Command::new(exe)
+            .args(worker_args(&pages.iter().copied().collect()))
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let mut done = HashSet::new();
+        let mut err = None;
+        for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+            let line = line?;
+            if let Some(message) = line.strip_prefix("error ") {
+                err = Some(message.to_string());
+            } else if let Ok(pos) = line.trim().parse::<usize>() {
+                done.insert(pos);
+            }
+        }
+        let status = child.wait()?;
+        pages.retain(|pos| !done.contains(pos));
+
+        if status.success() || pages.is_empty() {
+            break;
+        }
+
+        // Workers render pages in order so the first unfinished page is the one that failed.
+        let pos = pages.remove(0);
+        failures.push((
+            pos,
+            err.unwrap_or_else(|| format!("worker exited with {}", status)),
+        ));
+    }
+    Ok(failures)
+}
+
+// The current arguments with the worker's pages passed straight after the subcommand, where
+// they can't be mistaken for a positional argument or follow a `--`. Workers ignore --pages
+// and --jobs.
+fn worker_args(pages: &Intervals) -> Vec<OsString> {
+    let mut args: Vec<OsString> = env::args_os().skip(1).collect();
+    let pos = args.len().min(1);
+    args.splice(pos..pos, ["--worker".into(), pages.to_string().into()]);
+    args
+}
+
+// Maps page space to bitmap pixels so that `region` - in unrotated page space, which needn't
+// have its origin at the bottom left of the page - fills `size`.
+pub fn region_matrix(
+    page: &Page,
+    region: &Geometry,
+    size: (f32, f32),
+    rotate: bool,
+) -> Result<[f32; 6]> {
+    let rotation = page.rotation();
+    let (mut page_width, mut page_height) = (page.width(), page.height());
+    if rotation % 2 == 1 {
+        mem::swap(&mut page_width, &mut page_height);
+    }
+    // PDFium's page space is rotated so start by undoing that.
+    let unrotate = match rotation {
+        1 => [0., -1., 1., 0., 0., page_height],
+        2 => [-1., 0., 0., -1., page_width, page_height],
+        3 => [0., 1., -1., 0., page_width, 0.],
+        _ => [1., 0., 0., 1., 0., 0.],
+    };
+
+    let (mut width, mut height) = size;
+    if rotate && rotation % 2 == 1 {
+        mem::swap(&mut width, &mut height);
+    }
+    let scale_x = width / region.width as f32;
+    let scale_y = height / region.height as f32;
+    // PDFium measures from the top left of the page box rather than from the origin.
+    let (box_left, _, _, box_top) = page.bounding_box()?;
+    let left = region.x as f32 - box_left;
+    let top = box_top - (region.y + region.height) as f32;
+    let mut matrix = concat(
+        unrotate,
+        [scale_x, 0., 0., scale_y, -left * scale_x, -top * scale_y],
+    );
+
+    if rotate {
+        // Rotate the output a quarter turn clockwise at a time.
+        for _ in 0..rotation {
+            matrix = concat(matrix, [0., 1., -1., 0., height, 0.]);
+            mem::swap(&mut width, &mut height);
+        }
+    }
+    Ok(matrix)
+}
+
+// Returns the offset and length of a tile along one axis, including any overlap with its
+// neighbours.
+fn tile_span(pos: i32, tile_size: f32, overlap: f32, len: f32) -> (f32, f32) {
+    let start = (pos as f32 * tile_size - overlap).max(0.);
+    let end = ((pos + 1) as f32 * tile_size + overlap).min(len);
+    (start, end - start)
 }
 
 // width must be multiple of 4
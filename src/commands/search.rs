@@ -0,0 +1,118 @@
+use super::{resolve_labels, Command};
+use crate::bindings::{Document, Page, TextRect};
+use crate::syntax::{Color, Geometry, PageSelection};
+use anyhow::Result;
+use clap::Args;
+use pdfium_sys as p;
+use serde::Serialize;
+use std::fs::File;
+use std::path::PathBuf;
+
+/// search a PDF for text and print the hits as JSON
+#[derive(Args)]
+pub struct SearchCommand {
+    /// pages to search
+    #[clap(long)]
+    pages: Option<PageSelection>,
+    /// only match text with the same case as the query
+    #[clap(long)]
+    match_case: bool,
+    /// only match whole words
+    #[clap(long)]
+    whole_word: bool,
+    /// write a copy of the PDF with the hits highlighted to this path
+    #[clap(long)]
+    highlight: Option<PathBuf>,
+    /// highlight color as a hex string eg. ffff00 or ffff0080
+    #[clap(long, default_value = "ffff00")]
+    color: Color,
+    /// text to search for
+    query: String,
+    /// path to a PDF
+    pdf: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Hit {
+    page: usize,
+    // Character index of the start of the match within the page's text.
+    start: usize,
+    count: usize,
+    text: String,
+    // One per line or text run the match spans, in points from the bottom left of the page.
+    rects: Vec<Geometry>,
+}
+
+impl Command for SearchCommand {
+    fn execute(self) -> Result<()> {
+        let doc = Document::load(&self.pdf)?;
+
+        let page_count = doc.page_count();
+        let pages = if let Some(pages) = self.pages.as_ref() {
+            resolve_labels(&doc, pages)?
+        } else {
+            (1..=page_count).into()
+        };
+
+        let mut flags = 0;
+        if self.match_case {
+            flags |= p::FPDF_MATCHCASE;
+        }
+        if self.whole_word {
+            flags |= p::FPDF_MATCHWHOLEWORD;
+        }
+
+        let mut hits = vec![];
+        for pos in pages.iter(page_count) {
+            let page = doc.load_page(pos - 1)?;
+            let text_page = page.load_text_page()?;
+            let matches: Vec<_> = text_page.search(&self.query, flags)?.collect();
+
+            for (start, count) in matches {
+                let rects = text_page.rects(start, count);
+                if self.highlight.is_some() && !rects.is_empty() {
+                    self.add_highlight(&page, &rects)?;
+                }
+                hits.push(Hit {
+                    page: pos,
+                    start,
+                    count,
+                    text: text_page.text_range(start, count),
+                    rects: rects
+                        .iter()
+                        .map(|r| Geometry::from_bounds(r.left, r.bottom, r.right, r.top))
+                        .collect(),
+                });
+            }
+        }
+
+        if let Some(path) = self.highlight.as_ref() {
+            doc.save(&mut File::create(path)?)?;
+        }
+
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+
+        Ok(())
+    }
+}
+
+impl SearchCommand {
+    // Adds one highlight annotation per hit, covering each of its rectangles.
+    fn add_highlight(&self, page: &Page, rects: &[TextRect]) -> Result<()> {
+        let annot = page.create_annotation(p::FPDF_ANNOT_HIGHLIGHT)?;
+        let color = self.color;
+        annot.set_color(color.r, color.g, color.b, color.a)?;
+
+        let mut bounds = rects[0];
+        for rect in rects {
+            annot.append_attachment_points(rect)?;
+            bounds.left = bounds.left.min(rect.left);
+            bounds.top = bounds.top.max(rect.top);
+            bounds.right = bounds.right.max(rect.right);
+            bounds.bottom = bounds.bottom.min(rect.bottom);
+        }
+        annot.set_rect(&bounds)?;
+
+        Ok(())
+    }
+}
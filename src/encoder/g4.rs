@@ -0,0 +1,244 @@
+// A CCITT Group 4 (T.6) encoder for bilevel images.
+//
+// Each row is coded relative to the previous (reference) row using the pass, horizontal and
+// vertical modes described in the T.4/T.6 recommendations. `true` pixels are black.
+
+pub fn encode(rows: &[Vec<bool>], width: usize) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    let mut reference = vec![false; width];
+
+    for row in rows {
+        encode_row(&mut out, row, &reference);
+        reference.clone_from(row);
+    }
+
+    // End of facsimile block - two EOLs.
+    out.write("000000000001");
+    out.write("000000000001");
+    out.finish()
+}
+
+fn encode_row(out: &mut BitWriter, row: &[bool], reference: &[bool]) {
+    let width = row.len();
+    // `a0` starts on an imaginary white pixel just before the row.
+    let mut a0: isize = -1;
+    let mut color = false;
+
+    while a0 < width as isize {
+        let start = (a0 + 1) as usize;
+        let a1 = next_color(row, start, !color);
+        let b1 = next_change(reference, start, !color);
+        let b2 = next_color(reference, b1 + 1, color);
+
+        if b2 < a1 {
+            // Pass mode.
+            out.write("0001");
+            a0 = b2 as isize;
+        } else if (a1 as isize - b1 as isize).abs() <= 3 {
+            // Vertical mode.
+            out.write(VERTICAL[(a1 as isize - b1 as isize + 3) as usize]);
+            a0 = a1 as isize;
+            color = !color;
+        } else {
+            // Horizontal mode.
+            let a2 = next_color(row, a1 + 1, color);
+            out.write("001");
+            write_run(out, a1 - a0.max(0) as usize, color);
+            write_run(out, a2 - a1, !color);
+            a0 = a2 as isize;
+        }
+    }
+}
+
+// The position of the first pixel at or after `start` with the given color.
+fn next_color(row: &[bool], start: usize, color: bool) -> usize {
+    (start..row.len())
+        .find(|&pos| row[pos] == color)
+        .unwrap_or(row.len())
+}
+
+// The position of the first changing element at or after `start` that changes to the given
+// color.
+fn next_change(row: &[bool], start: usize, color: bool) -> usize {
+    (start..row.len())
+        .find(|&pos| row[pos] == color && (pos == 0 && color || pos > 0 && row[pos - 1] != color))
+        .unwrap_or(row.len())
+}
+
+fn write_run(out: &mut BitWriter, mut run: usize, black: bool) {
+    let (terminating, makeup) = if black {
+        (BLACK_TERMINATING, BLACK_MAKEUP)
+    } else {
+        (WHITE_TERMINATING, WHITE_MAKEUP)
+    };
+    while run >= 2560 {
+        out.write(EXTENDED_MAKEUP[EXTENDED_MAKEUP.len() - 1]);
+        run -= 2560;
+    }
+    if run >= 64 {
+        let pos = run / 64 - 1;
+        if pos < makeup.len() {
+            out.write(makeup[pos]);
+        } else {
+            out.write(EXTENDED_MAKEUP[pos - makeup.len()]);
+        }
+        run %= 64;
+    }
+    out.write(terminating[run]);
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    byte: u8,
+    len: u8,
+}
+
+impl BitWriter {
+    // Codes are written as strings of '0'/'1' so they can be compared against the spec tables.
+    fn write(&mut self, code: &str) {
+        for bit in code.bytes() {
+            self.byte = self.byte << 1 | (bit == b'1') as u8;
+            self.len += 1;
+            if self.len == 8 {
+                self.bytes.push(self.byte);
+                self.byte = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.byte << (8 - self.len));
+        }
+        self.bytes
+    }
+}
+
+// Indexed by `a1 - b1 + 3`.
+#[rustfmt::skip]
+const VERTICAL: [&str; 7] = [
+    "0000010", "000010", "010", "1", "011", "000011", "0000011",
+];
+
+// Run lengths 0-63.
+#[rustfmt::skip]
+const WHITE_TERMINATING: [&str; 64] = [
+    "00110101", "000111", "0111", "1000", "1011", "1100", "1110", "1111", "10011", "10100",
+    "00111", "01000", "001000", "000011", "110100", "110101", "101010", "101011", "0100111",
+    "0001100", "0001000", "0010111", "0000011", "0000100", "0101000", "0101011", "0010011",
+    "0100100", "0011000", "00000010", "00000011", "00011010", "00011011", "00010010",
+    "00010011", "00010100", "00010101", "00010110", "00010111", "00101000", "00101001",
+    "00101010", "00101011", "00101100", "00101101", "00000100", "00000101", "00001010",
+    "00001011", "01010010", "01010011", "01010100", "01010101", "00100100", "00100101",
+    "01011000", "01011001", "01011010", "01011011", "01001010", "01001011", "00110010",
+    "00110011", "00110100",
+];
+
+#[rustfmt::skip]
+const BLACK_TERMINATING: [&str; 64] = [
+    "0000110111", "010", "11", "10", "011", "0011", "0010", "00011", "000101", "000100",
+    "0000100", "0000101", "0000111", "00000100", "00000111", "000011000", "0000010111",
+    "0000011000", "0000001000", "00001100111", "00001101000", "00001101100", "00000110111",
+    "00000101000", "00000010111", "00000011000", "000011001010", "000011001011",
+    "000011001100", "000011001101", "000001101000", "000001101001", "000001101010",
+    "000001101011", "000011010010", "000011010011", "000011010100", "000011010101",
+    "000011010110", "000011010111", "000001101100", "000001101101", "000011011010",
+    "000011011011", "000001010100", "000001010101", "000001010110", "000001010111",
+    "000001100100", "000001100101", "000001010010", "000001010011", "000000100100",
+    "000000110111", "000000111000", "000000100111", "000000101000", "000001011000",
+    "000001011001", "000000101011", "000000101100", "000001011010", "000001100110",
+    "000001100111",
+];
+
+// Run lengths 64-1728 in steps of 64.
+#[rustfmt::skip]
+const WHITE_MAKEUP: [&str; 27] = [
+    "11011", "10010", "010111", "0110111", "00110110", "00110111", "01100100", "01100101",
+    "01101000", "01100111", "011001100", "011001101", "011010010", "011010011", "011010100",
+    "011010101", "011010110", "011010111", "011011000", "011011001", "011011010", "011011011",
+    "010011000", "010011001", "010011010", "011000", "010011011",
+];
+
+#[rustfmt::skip]
+const BLACK_MAKEUP: [&str; 27] = [
+    "0000001111", "000011001000", "000011001001", "000001011011", "000000110011",
+    "000000110100", "000000110101", "0000001101100", "0000001101101", "0000001001010",
+    "0000001001011", "0000001001100", "0000001001101", "0000001110010", "0000001110011",
+    "0000001110100", "0000001110101", "0000001110110", "0000001110111", "0000001010010",
+    "0000001010011", "0000001010100", "0000001010101", "0000001011010", "0000001011011",
+    "0000001100100", "0000001100101",
+];
+
+// Run lengths 1792-2560 in steps of 64, shared by both colors.
+#[rustfmt::skip]
+const EXTENDED_MAKEUP: [&str; 13] = [
+    "00000001000", "00000001100", "00000001101", "000000010010", "000000010011",
+    "000000010100", "000000010101", "000000010110", "000000010111", "000000011100",
+    "000000011101", "000000011110", "000000011111",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // End of facsimile block.
+    const EOFB: &str = "000000000001000000000001";
+
+    fn bits(code: &str) -> Vec<u8> {
+        let mut out = BitWriter::default();
+        out.write(code);
+        out.finish()
+    }
+
+    fn row(pixels: &str) -> Vec<bool> {
+        pixels.chars().map(|c| c == '#').collect()
+    }
+
+    #[test]
+    fn empty_image() {
+        assert_eq!(encode(&[], 8), [0x00, 0x10, 0x01]);
+        assert_eq!(encode(&[], 8), bits(EOFB));
+    }
+
+    #[test]
+    fn white_row() {
+        // V0 - the row doesn't change where the imaginary white row above doesn't.
+        assert_eq!(encode(&[row("........")], 8), bits(&format!("1{}", EOFB)));
+    }
+
+    #[test]
+    fn horizontal_then_vertical() {
+        let rows = [row("..###..."), row("..###...")];
+        // Horizontal mode with runs of 2 white and 3 black then V0 to the end of the row,
+        // then three V0s for the same row again.
+        let code = format!("001{}{}1{}{}", "0111", "10", "111", EOFB);
+        assert_eq!(encode(&rows, 8), bits(&code));
+        assert_eq!(encode(&rows[..1], 8), [0x2f, 0x40, 0x04, 0x00, 0x40]);
+    }
+
+    #[test]
+    fn vertical_offsets() {
+        let rows = [row("..###..."), row("...###..")];
+        // The second row is VR1 twice, each change moving one pixel right, then V0.
+        let code = format!("0010111101{}{}", "0110111", EOFB);
+        assert_eq!(encode(&rows, 8), bits(&code));
+    }
+
+    #[test]
+    fn long_runs() {
+        let mut out = BitWriter::default();
+        write_run(&mut out, 64, false);
+        assert_eq!(out.finish(), bits(&format!("{}{}", "11011", "00110101")));
+
+        let mut out = BitWriter::default();
+        write_run(&mut out, 1797, true);
+        assert_eq!(out.finish(), bits(&format!("{}{}", "00000001000", "0011")));
+
+        let mut out = BitWriter::default();
+        write_run(&mut out, 2560 + 2560 + 1, false);
+        let code = format!("{}{}{}", "000000011111", "000000011111", "000111");
+        assert_eq!(out.finish(), bits(&code));
+    }
+}
@@ -0,0 +1,115 @@
+mod g4;
+pub mod tiff;
+
+use crate::bindings::Bitmap;
+use crate::syntax::ImageFormat;
+use anyhow::{anyhow, Result};
+use image::GenericImageView;
+use ravif::{Img, RGB8, RGBA8};
+use std::fs;
+use std::path::Path;
+use tiff::{TiffEncoder, TiffOptions};
+
+pub trait Encoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub quality: u8,
+    // Only affects WebP.
+    pub lossless: bool,
+    pub tiff: TiffOptions,
+}
+
+pub fn new(format: ImageFormat, options: &Options) -> Box<dyn Encoder> {
+    match format {
+        ImageFormat::Jpg => Box::new(JpegEncoder {
+            quality: options.quality,
+        }),
+        ImageFormat::Png => Box::new(ImageEncoder(image::ImageFormat::Png)),
+        ImageFormat::Tiff => Box::new(TiffEncoder::new(options.tiff)),
+        ImageFormat::Pnm => Box::new(ImageEncoder(image::ImageFormat::Pnm)),
+        ImageFormat::Bmp => Box::new(ImageEncoder(image::ImageFormat::Bmp)),
+        ImageFormat::Webp => Box::new(WebpEncoder {
+            quality: options.quality,
+            lossless: options.lossless,
+        }),
+        ImageFormat::Avif => Box::new(AvifEncoder {
+            quality: options.quality,
+        }),
+    }
+}
+
+// JPEGs go through `libjpegturbo` directly since it's much faster than `image`.
+struct JpegEncoder {
+    quality: u8,
+}
+
+impl Encoder for JpegEncoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()> {
+        bmp.write_image(path, self.quality)
+    }
+}
+
+struct ImageEncoder(image::ImageFormat);
+
+impl Encoder for ImageEncoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()> {
+        bmp.to_image()?.save_with_format(path, self.0)?;
+        Ok(())
+    }
+}
+
+// The `image` version we depend on can decode but not encode WebP and AVIF so they go through
+// `libwebp` and `ravif` instead.
+struct WebpEncoder {
+    quality: u8,
+    lossless: bool,
+}
+
+impl Encoder for WebpEncoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()> {
+        let img = bmp.to_image()?;
+        let (width, height) = img.dimensions();
+        let (pixels, layout) = if img.color().has_alpha() {
+            (img.to_rgba8().into_raw(), webp::PixelLayout::Rgba)
+        } else {
+            (img.to_rgb8().into_raw(), webp::PixelLayout::Rgb)
+        };
+        let data = webp::Encoder::new(&pixels, layout, width, height)
+            .encode_simple(self.lossless, self.quality.min(100) as f32)
+            .map_err(|err| anyhow!("failed to encode WebP: {:?}", err))?;
+        fs::write(path, &*data)?;
+        Ok(())
+    }
+}
+
+struct AvifEncoder {
+    quality: u8,
+}
+
+impl Encoder for AvifEncoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()> {
+        let img = bmp.to_image()?;
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let encoder = ravif::Encoder::new().with_quality(self.quality.clamp(1, 100) as f32);
+        let encoded = if img.color().has_alpha() {
+            let pixels: Vec<RGBA8> = img
+                .to_rgba8()
+                .pixels()
+                .map(|px| RGBA8::new(px[0], px[1], px[2], px[3]))
+                .collect();
+            encoder.encode_rgba(Img::new(&pixels[..], width, height))?
+        } else {
+            let pixels: Vec<RGB8> = img
+                .to_rgb8()
+                .pixels()
+                .map(|px| RGB8::new(px[0], px[1], px[2]))
+                .collect();
+            encoder.encode_rgb(Img::new(&pixels[..], width, height))?
+        };
+        fs::write(path, encoded.avif_file)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use super::{g4, Encoder};
+use crate::bindings::Bitmap;
+use crate::syntax::{ColorMode, TiffCompression};
+use ::tiff::encoder::{DirectoryEncoder, Rational, TiffEncoder as RawEncoder, TiffValue};
+use ::tiff::tags::{CompressionMethod, PhotometricInterpretation, ResolutionUnit, Tag};
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel, RgbaImage};
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+pub struct TiffOptions {
+    pub color_mode: ColorMode,
+    pub compression: TiffCompression,
+    // Gray values below the threshold become black when converting to bilevel.
+    pub threshold: u8,
+    // Use Floyd-Steinberg dithering (around the threshold) when converting to bilevel.
+    pub dither: bool,
+    // Written as the X/Y resolution if known.
+    pub dpi: Option<u32>,
+}
+
+impl Default for TiffOptions {
+    fn default() -> Self {
+        Self {
+            color_mode: ColorMode::Color,
+            compression: TiffCompression::None,
+            threshold: 128,
+            dither: false,
+            dpi: None,
+        }
+    }
+}
+
+impl TiffOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.compression == TiffCompression::G4 && self.color_mode != ColorMode::Bilevel {
+            return Err(anyhow!("g4 compression requires the bilevel color mode"));
+        }
+        Ok(())
+    }
+}
+
+pub struct TiffEncoder {
+    options: TiffOptions,
+}
+
+impl TiffEncoder {
+    pub fn new(options: TiffOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Encoder for TiffEncoder {
+    fn encode(&self, bmp: &Bitmap, path: &Path) -> Result<()> {
+        let mut writer = TiffWriter::create(path, self.options)?;
+        writer.write_page(bmp)
+    }
+}
+
+// Writes each page as a separate IFD so the result is a multi-page TIFF.
+pub struct TiffWriter<W: Write + Seek> {
+    encoder: RawEncoder<W>,
+    options: TiffOptions,
+}
+
+impl TiffWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, options: TiffOptions) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), options)
+    }
+}
+
+impl<W: Write + Seek> TiffWriter<W> {
+    pub fn new(writer: W, options: TiffOptions) -> Result<Self> {
+        options.validate()?;
+        Ok(Self {
+            encoder: RawEncoder::new(writer)?,
+            options,
+        })
+    }
+
+    pub fn write_page(&mut self, bmp: &Bitmap) -> Result<()> {
+        let img = bmp.to_image()?;
+        let (width, height) = img.dimensions();
+        let options = self.options;
+        let mut dir = self.encoder.new_directory()?;
+
+        let (photometric, bits_per_sample, strip): (_, &[u16], _) = match options.color_mode {
+            ColorMode::Color => {
+                if let DynamicImage::ImageRgba8(img) = img {
+                    dir.write_tag(Tag::ExtraSamples, 2u16)?;
+                    (
+                        PhotometricInterpretation::RGB,
+                        &[8, 8, 8, 8],
+                        img.into_raw(),
+                    )
+                } else {
+                    let img = img.into_rgb8();
+                    (PhotometricInterpretation::RGB, &[8, 8, 8], img.into_raw())
+                }
+            }
+            ColorMode::Gray => {
+                let img = flatten(img);
+                (PhotometricInterpretation::BlackIsZero, &[8], img.into_raw())
+            }
+            ColorMode::Bilevel => {
+                let rows = to_bilevel(flatten(img), options.threshold, options.dither);
+                let strip = match options.compression {
+                    TiffCompression::None => pack_rows(&rows),
+                    TiffCompression::G4 => g4::encode(&rows, width as usize),
+                };
+                (PhotometricInterpretation::WhiteIsZero, &[1], strip)
+            }
+        };
+        let compression = match options.compression {
+            TiffCompression::None => CompressionMethod::None,
+            TiffCompression::G4 => CompressionMethod::Fax4,
+        };
+
+        let offset = dir.write_data(&strip[..])?;
+        write_tag(&mut dir, Tag::ImageWidth, width)?;
+        write_tag(&mut dir, Tag::ImageLength, height)?;
+        write_tag(&mut dir, Tag::BitsPerSample, bits_per_sample)?;
+        write_tag(&mut dir, Tag::Compression, compression.to_u16())?;
+        write_tag(
+            &mut dir,
+            Tag::PhotometricInterpretation,
+            photometric.to_u16(),
+        )?;
+        write_tag(&mut dir, Tag::StripOffsets, offset as u32)?;
+        write_tag(&mut dir, Tag::SamplesPerPixel, bits_per_sample.len() as u16)?;
+        write_tag(&mut dir, Tag::RowsPerStrip, height)?;
+        write_tag(&mut dir, Tag::StripByteCounts, strip.len() as u32)?;
+        if let Some(dpi) = options.dpi {
+            let resolution = Rational { n: dpi, d: 1 };
+            write_tag(&mut dir, Tag::XResolution, resolution.clone())?;
+            write_tag(&mut dir, Tag::YResolution, resolution)?;
+            write_tag(&mut dir, Tag::ResolutionUnit, ResolutionUnit::Inch.to_u16())?;
+        }
+        dir.finish()?;
+
+        Ok(())
+    }
+}
+
+fn write_tag<W: Write + Seek, T: TiffValue>(
+    dir: &mut DirectoryEncoder<W>,
+    tag: Tag,
+    value: T,
+) -> Result<()> {
+    dir.write_tag(tag, value)?;
+    Ok(())
+}
+
+// Converts to grayscale, compositing any transparency over white.
+fn flatten(img: DynamicImage) -> GrayImage {
+    let img: RgbaImage = img.into_rgba8();
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let px = img.get_pixel(x, y);
+        let luma = px.to_luma()[0] as u32;
+        let alpha = px[3] as u32;
+        image::Luma([((luma * alpha + 255 * (255 - alpha)) / 255) as u8])
+    })
+}
+
+// Returns a row of `true` (black) / `false` (white) values for each row of pixels.
+fn to_bilevel(img: GrayImage, threshold: u8, dither: bool) -> Vec<Vec<bool>> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    // `chunks` panics on a width of zero.
+    if width == 0 || height == 0 {
+        return vec![vec![]; height];
+    }
+    if !dither {
+        return img
+            .into_raw()
+            .chunks(width)
+            .map(|row| row.iter().map(|&luma| luma < threshold).collect())
+            .collect();
+    }
+
+    let mut errors: Vec<i32> = img.into_raw().into_iter().map(|luma| luma as i32).collect();
+    let mut rows = Vec::with_capacity(height);
+    for y in 0..height {
+        let mut row = Vec::with_capacity(width);
+        for x in 0..width {
+            let old = errors[y * width + x];
+            let black = old < threshold as i32;
+            row.push(black);
+            let err = old - if black { 0 } else { 255 };
+            let mut spread = |dx: isize, dy: usize, weight: i32| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    errors[(y + dy) * width + nx as usize] += err * weight / 16;
+                }
+            };
+            spread(1, 0, 7);
+            spread(-1, 1, 3);
+            spread(0, 1, 5);
+            spread(1, 1, 1);
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+// Packs rows into bytes, most significant bit first, padding each row to a byte boundary.
+fn pack_rows(rows: &[Vec<bool>]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for row in rows {
+        for chunk in row.chunks(8) {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (pos, &black)| byte | (black as u8) << (7 - pos));
+            bytes.push(byte);
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bilevel_threshold() {
+        let img = GrayImage::from_raw(3, 2, vec![0, 127, 128, 255, 10, 200]).unwrap();
+        assert_eq!(
+            to_bilevel(img, 128, false),
+            [[true, true, false], [false, true, false]]
+        );
+    }
+
+    #[test]
+    fn bilevel_empty() {
+        for dither in [false, true] {
+            let rows = to_bilevel(GrayImage::new(0, 3), 128, dither);
+            assert_eq!(rows, vec![Vec::<bool>::new(); 3]);
+            assert!(to_bilevel(GrayImage::new(4, 0), 128, dither).is_empty());
+        }
+    }
+
+    #[test]
+    fn packed_rows() {
+        let row = |pixels: &str| pixels.chars().map(|c| c == '#').collect::<Vec<_>>();
+        let rows = [row("#.......#"), row("..##")];
+        assert_eq!(pack_rows(&rows), [0x80, 0x80, 0x30]);
+        assert!(pack_rows(&[vec![]]).is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+use crate::bindings::{Page, TextPage};
+use crate::syntax::Geometry;
+use crate::tables;
+use crate::text::{self, Block, Word};
+use pdfium_sys as p;
+use serde::Serialize;
+
+// Lines of a paragraph are at most this many line heights apart.
+const PARAGRAPH_GAP: f64 = 0.8;
+// Text at least this much larger than the body text is a heading.
+const HEADING_SCALE: f64 = 1.15;
+// Headings are short.
+const MAX_HEADING_LINES: usize = 3;
+// Groups of paths closer than this, in points, belong to the same figure.
+const FIGURE_GAP: f64 = 6.;
+// Smaller groups of paths are decoration, eg. bullets or underlines.
+const MIN_FIGURE_SIZE: f64 = 24.;
+// Groups of paths with more of their area covered by text are text boxes, not figures.
+const MAX_FIGURE_TEXT: f64 = 0.3;
+// Characters which start the items of unordered lists.
+const BULLETS: &str = "•◦▪▫■□●○‣⁃–-*·";
+// Font descriptor flag - see section 5.7.1 of the PDF reference.
+const FORCE_BOLD: i32 = 1 << 18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    Heading,
+    Paragraph,
+    ListItem,
+    Image,
+    Figure,
+    Table,
+}
+
+#[derive(Serialize)]
+pub struct Region {
+    pub kind: RegionKind,
+    // In points from the bottom left of the page.
+    pub bounds: Geometry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    // From 1 for the largest headings on the page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<Vec<String>>>,
+    // The index of an image's page object, unless it's inside a form XObject.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<usize>,
+}
+
+impl Region {
+    fn new(kind: RegionKind, bounds: Geometry) -> Self {
+        Self {
+            kind,
+            bounds,
+            text: None,
+            level: None,
+            font_size: None,
+            rows: None,
+            object: None,
+        }
+    }
+}
+
+// Splits a page into regions, in reading order.
+//
+// Tables come from `tables::find_tables`, images from the page's image objects and figures
+// from clusters of vector paths. The remaining text is split into lines by `text::blocks`
+// and the lines are grouped into paragraphs, with short runs of larger or bold text taken
+// as headings.
+pub fn analyze(page: &Page, text_page: &TextPage) -> Vec<Region> {
+    let lines = text::lines(text_page);
+    let mut words: Vec<&Word> = lines.iter().flat_map(|line| line.words.iter()).collect();
+    let body_size = if words.is_empty() {
+        0.
+    } else {
+        text::median(words.iter().map(|word| word.chars[0].font_size))
+    };
+
+    let mut regions = vec![];
+    for table in tables::find_tables(page, text_page) {
+        words.retain(|word| !contains(&table.bounds, &word.bounds));
+        let mut region = Region::new(RegionKind::Table, table.bounds);
+        region.rows = Some(table.rows);
+        regions.push(region);
+    }
+
+    let (images, paths) = graphics(page);
+    for (object, bounds) in images {
+        // Text over images is kept as text, eg. the OCR layer of a scanned page.
+        let mut region = Region::new(RegionKind::Image, bounds);
+        region.object = object;
+        regions.push(region);
+    }
+
+    let paths: Vec<Geometry> = paths
+        .into_iter()
+        .filter(|bounds| {
+            !regions
+                .iter()
+                .any(|region| contains(&region.bounds, bounds))
+        })
+        .collect();
+    for figure in cluster(paths, FIGURE_GAP) {
+        if figure.width < MIN_FIGURE_SIZE || figure.height < MIN_FIGURE_SIZE {
+            continue;
+        }
+        let text_area: f64 = words
+            .iter()
+            .filter(|word| contains(&figure, &word.bounds))
+            .map(|word| word.bounds.width * word.bounds.height)
+            .sum();
+        if text_area > figure.width * figure.height * MAX_FIGURE_TEXT {
+            continue;
+        }
+        // Labels inside a figure are part of it.
+        words.retain(|word| !contains(&figure, &word.bounds));
+        regions.push(Region::new(RegionKind::Figure, figure));
+    }
+
+    regions.extend(paragraphs(text::blocks(words), body_size));
+
+    let bounds: Vec<Geometry> = regions.iter().map(|region| region.bounds).collect();
+    let order = text::reading_order(&bounds, body_size * text::COLUMN_GAP);
+    let mut regions: Vec<Option<Region>> = regions.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|i| regions[i].take())
+        .collect()
+}
+
+// The bounds of the page's image objects, along with the indices of those at the top level of
+// the page, and of its paths. Objects inside form XObjects are included.
+fn graphics(page: &Page) -> (Vec<(Option<usize>, Geometry)>, Vec<Geometry>) {
+    let page_area = (page.width() * page.height()) as f64;
+    let (mut images, mut paths) = (vec![], vec![]);
+    for nested in page.nested_objects() {
+        let bounds = match nested.object.bounds() {
+            Ok((left, bottom, right, top)) => {
+                Geometry::from_bounds(left as f64, bottom as f64, right as f64, top as f64)
+                    .transform(nested.matrix)
+            }
+            Err(_) => continue,
+        };
+        match nested.object.type_() as u32 {
+            p::FPDF_PAGEOBJ_IMAGE => images.push((nested.pos, bounds)),
+            // Page backgrounds and frames aren't figures.
+            p::FPDF_PAGEOBJ_PATH if bounds.width * bounds.height < page_area * 0.8 => {
+                paths.push(bounds)
+            }
+            _ => {}
+        }
+    }
+    (images, paths)
+}
+
+// Merges boxes which are closer than `gap` until none are.
+fn cluster(mut boxes: Vec<Geometry>, gap: f64) -> Vec<Geometry> {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        let mut i = 0;
+        while i < boxes.len() {
+            let mut j = i + 1;
+            while j < boxes.len() {
+                if near(&boxes[i], &boxes[j], gap) {
+                    let other = boxes.swap_remove(j);
+                    boxes[i] = boxes[i].union(&other);
+                    merged = true;
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+    }
+    boxes
+}
+
+fn paragraphs(blocks: Vec<Block>, body_size: f64) -> Vec<Region> {
+    let mut groups: Vec<Vec<Block>> = vec![];
+    for block in blocks {
+        match groups.last_mut() {
+            Some(group) if continues(group, &block) => group.push(block),
+            _ => groups.push(vec![block]),
+        }
+    }
+
+    let mut regions: Vec<Region> = groups
+        .into_iter()
+        .map(|group| {
+            let bounds = group
+                .iter()
+                .skip(1)
+                .fold(group[0].bounds, |bounds, block| bounds.union(&block.bounds));
+            let words: Vec<&Word> = group
+                .iter()
+                .flat_map(|block| block.words.iter().copied())
+                .collect();
+            let font_size = text::median(words.iter().map(|word| word.chars[0].font_size));
+            let bold = words.iter().all(|word| is_bold(word));
+
+            let is_heading = group.len() <= MAX_HEADING_LINES
+                && (font_size >= body_size * HEADING_SCALE || (bold && font_size >= body_size));
+            let text = join_lines(&group);
+            let kind = if is_heading {
+                RegionKind::Heading
+            } else if list_marker(&text).is_some() {
+                RegionKind::ListItem
+            } else {
+                RegionKind::Paragraph
+            };
+            let mut region = Region::new(kind, bounds);
+            region.text = Some(text);
+            region.font_size = Some(font_size);
+            region
+        })
+        .collect();
+
+    // Larger headings get lower levels, with bold text at the body size last.
+    let mut sizes: Vec<f64> = regions
+        .iter()
+        .filter(|region| region.kind == RegionKind::Heading)
+        .filter_map(|region| region.font_size)
+        .map(|size| size.round())
+        .collect();
+    sizes.sort_by(|a, b| b.total_cmp(a));
+    sizes.dedup();
+    for region in regions.iter_mut() {
+        if region.kind == RegionKind::Heading {
+            let size = region.font_size.unwrap_or(0.).round();
+            let level = sizes.iter().position(|&s| s == size).unwrap_or(0) + 1;
+            region.level = Some(level.min(6) as u8);
+        }
+    }
+    regions
+}
+
+// Whether `next` is the next line of the same paragraph as the lines in `group`.
+fn continues(group: &[Block], next: &Block) -> bool {
+    let prev = &group[group.len() - 1];
+    let size = |block: &Block| block.words[0].chars[0].font_size;
+    let (prev_size, next_size) = (size(prev), size(next));
+    let line_height = prev.bounds.height.max(next.bounds.height);
+    let gap = prev.bounds.y - next.bounds.top();
+    prev.origin == next.origin
+        && !starts_item(group, next)
+        && gap >= -line_height / 2.
+        && gap <= line_height * PARAGRAPH_GAP
+        && (prev_size - next_size).abs() <= prev_size.max(next_size) * 0.1
+        && next.bounds.x < prev.bounds.right()
+        && prev.bounds.x < next.bounds.right()
+}
+
+// Joins the lines of a paragraph with spaces, rejoining words hyphenated across them.
+fn join_lines(lines: &[Block]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        let line_text = line
+            .words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let hyphenated = text.ends_with('-')
+            && text[..text.len() - 1].ends_with(char::is_alphabetic)
+            && line_text.starts_with(char::is_lowercase);
+        if hyphenated {
+            text.pop();
+        } else if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&line_text);
+    }
+    text
+}
+
+// Splits a list item's text into whether the list is ordered and the text after the marker,
+// eg. "2. Terms" gives (true, "Terms") and "• Terms" gives (false, "Terms").
+pub fn list_marker(text: &str) -> Option<(bool, &str)> {
+    let marker_len = text.find(char::is_whitespace)?;
+    let (marker, rest) = text.split_at(marker_len);
+    let rest = rest.trim_start();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut chars = marker.chars();
+    let first = chars.next()?;
+    if chars.as_str().is_empty() && BULLETS.contains(first) {
+        return Some((false, rest));
+    }
+    // Numbers and single letters followed by "." or ")", eg. "12." or "a)".
+    let label = marker
+        .strip_suffix('.')
+        .or_else(|| marker.strip_suffix(')'))?;
+    let is_number =
+        !label.is_empty() && label.len() <= 3 && label.chars().all(|c| c.is_ascii_digit());
+    let is_letter = label.len() == 1 && label.chars().all(|c| c.is_ascii_lowercase());
+    if is_number || (is_letter && marker.ends_with(')')) {
+        Some((true, rest))
+    } else {
+        None
+    }
+}
+
+// Whether `next` starts a new list item rather than continuing `group`. Wrapped text can
+// start with something which looks like a marker, eg. a dash, so markers only count after a
+// line which ends a sentence or clause, or when they line up with the marker of a list item.
+fn starts_item(group: &[Block], next: &Block) -> bool {
+    if !is_list_marker(next.words[0]) {
+        return false;
+    }
+    let prev = &group[group.len() - 1];
+    let ends_line = prev.words[prev.words.len() - 1]
+        .text
+        .ends_with(['.', ':', ';', '!', '?']);
+    let first = &group[0];
+    let tolerance = first.words[0].chars[0].font_size / 2.;
+    let aligned = is_list_marker(first.words[0])
+        && (first.words[0].bounds.x - next.words[0].bounds.x).abs() <= tolerance;
+    ends_line || aligned
+}
+
+fn is_list_marker(word: &Word) -> bool {
+    // Markers are checked with some following text since a lone marker isn't a list item.
+    list_marker(&format!("{} _", word.text)).is_some()
+}
+
+pub fn is_bold(word: &Word) -> bool {
+    let c = &word.chars[0];
+    c.font_weight >= 600 || c.font_flags & FORCE_BOLD != 0 || c.font_name.contains("Bold")
+}
+
+// Whether the centre of `inner` lies within `outer`.
+fn contains(outer: &Geometry, inner: &Geometry) -> bool {
+    let (x, y) = (inner.x + inner.width / 2., inner.y + inner.height / 2.);
+    x >= outer.x && x <= outer.right() && y >= outer.y && y <= outer.top()
+}
+
+fn near(a: &Geometry, b: &Geometry, gap: f64) -> bool {
+    a.x - gap <= b.right() && b.x - gap <= a.right() && a.y - gap <= b.top() && b.y - gap <= a.top()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unordered_list_markers() {
+        assert_eq!(list_marker("• Terms"), Some((false, "Terms")));
+        assert_eq!(
+            list_marker("-   Terms and conditions"),
+            Some((false, "Terms and conditions"))
+        );
+        assert_eq!(list_marker("– Terms"), Some((false, "Terms")));
+        assert_eq!(list_marker("* Terms"), Some((false, "Terms")));
+    }
+
+    #[test]
+    fn ordered_list_markers() {
+        assert_eq!(list_marker("2. Terms"), Some((true, "Terms")));
+        assert_eq!(list_marker("12) Terms"), Some((true, "Terms")));
+        assert_eq!(list_marker("a) Terms"), Some((true, "Terms")));
+    }
+
+    #[test]
+    fn not_list_markers() {
+        // Markers need some text after them.
+        assert_eq!(list_marker("•"), None);
+        assert_eq!(list_marker("2. "), None);
+        assert_eq!(list_marker("Terms"), None);
+        assert_eq!(list_marker("-Terms apply"), None);
+        assert_eq!(list_marker("•• Terms"), None);
+        // Years and single letters with full stops are more likely to end sentences.
+        assert_eq!(list_marker("2023. Terms"), None);
+        assert_eq!(list_marker("a. Terms"), None);
+        assert_eq!(list_marker("A) Terms"), None);
+        assert_eq!(list_marker(". Terms"), None);
+    }
+}
@@ -1,6 +1,10 @@
 mod bindings;
 mod commands;
+mod encoder;
+mod layout;
 mod syntax;
+mod tables;
+mod text;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
@@ -18,11 +22,25 @@ struct Cli {
 enum CliCommand {
     Render(commands::RenderCommand),
     PageCount(commands::PageCountCommand),
+    Info(commands::InfoCommand),
+    Outline(commands::OutlineCommand),
+    Fonts(commands::FontsCommand),
+    Attachments(commands::AttachmentsCommand),
     AddImage(commands::AddImageCommand),
     AddText(commands::AddTextCommand),
     Edit(commands::EditCommand),
     ExtractImages(commands::ExtractImagesCommand),
+    ExtractTables(commands::ExtractTablesCommand),
+    ExtractText(commands::ExtractTextCommand),
+    ExtractZones(commands::ExtractZonesCommand),
     Create(commands::CreateCommand),
+    Montage(commands::MontageCommand),
+    Diff(commands::DiffCommand),
+    Search(commands::SearchCommand),
+    Grep(commands::GrepCommand),
+    AnalyzeLayout(commands::AnalyzeLayoutCommand),
+    ExportMarkdown(commands::ExportMarkdownCommand),
+    ExportHtml(commands::ExportHtmlCommand),
 }
 
 impl Cli {
@@ -30,11 +48,25 @@ impl Cli {
         match self.command {
             CliCommand::Render(c) => c.execute(),
             CliCommand::PageCount(c) => c.execute(),
+            CliCommand::Info(c) => c.execute(),
+            CliCommand::Outline(c) => c.execute(),
+            CliCommand::Fonts(c) => c.execute(),
+            CliCommand::Attachments(c) => c.execute(),
             CliCommand::AddImage(c) => c.execute(),
             CliCommand::AddText(c) => c.execute(),
             CliCommand::Edit(c) => c.execute(),
             CliCommand::ExtractImages(c) => c.execute(),
+            CliCommand::ExtractTables(c) => c.execute(),
+            CliCommand::ExtractText(c) => c.execute(),
+            CliCommand::ExtractZones(c) => c.execute(),
             CliCommand::Create(c) => c.execute(),
+            CliCommand::Montage(c) => c.execute(),
+            CliCommand::Diff(c) => c.execute(),
+            CliCommand::Search(c) => c.execute(),
+            CliCommand::Grep(c) => c.execute(),
+            CliCommand::AnalyzeLayout(c) => c.execute(),
+            CliCommand::ExportMarkdown(c) => c.execute(),
+            CliCommand::ExportHtml(c) => c.execute(),
         }
     }
 }
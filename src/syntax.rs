@@ -1,5 +1,7 @@
 use anyhow::{anyhow, Error, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::FromIterator;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
@@ -36,6 +38,39 @@ impl From<RangeInclusive<usize>> for Intervals {
     }
 }
 
+// Collapses runs of consecutive numbers into a single interval.
+impl FromIterator<usize> for Intervals {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut items: Vec<Interval> = vec![];
+        for n in iter {
+            match items.last_mut() {
+                Some(Interval { end: Some(end), .. }) if *end + 1 == n => *end = n,
+                _ => items.push(Interval {
+                    start: n,
+                    end: Some(n),
+                }),
+            }
+        }
+        Self { items }
+    }
+}
+
+impl fmt::Display for Intervals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (pos, iv) in self.items.iter().enumerate() {
+            if pos > 0 {
+                write!(f, ",")?;
+            }
+            match iv.end {
+                Some(end) if end == iv.start => write!(f, "{}", iv.start)?,
+                Some(end) => write!(f, "{}-{}", iv.start, end)?,
+                None => write!(f, "{}-", iv.start)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl FromStr for Intervals {
     type Err = Error;
 
@@ -94,6 +129,138 @@ impl<'a> Iterator for IntervalsIterator<'a> {
     }
 }
 
+// Pages as selected on the command line, where they can be given by their label as well as
+// their number, eg. "1-3,label:iv,label:x-". They're resolved to `Intervals` for a particular
+// document by `resolve_labels`.
+#[derive(Clone)]
+pub struct PageSelection {
+    items: Vec<Selection>,
+}
+
+// Like `Interval`, `end` is None for ranges left open.
+#[derive(Clone)]
+struct Selection {
+    start: Bound,
+    end: Option<Bound>,
+}
+
+#[derive(Clone)]
+enum Bound {
+    Page(usize),
+    Label(String),
+}
+
+impl FromStr for Bound {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.strip_prefix("label:") {
+            Some("") => Err(anyhow!("missing page label")),
+            Some(label) => Ok(Self::Label(label.to_string())),
+            None => Ok(Self::Page(s.parse()?)),
+        }
+    }
+}
+
+impl PageSelection {
+    pub fn has_labels(&self) -> bool {
+        self.items.iter().any(|item| {
+            matches!(item.start, Bound::Label(_)) || matches!(item.end, Some(Bound::Label(_)))
+        })
+    }
+
+    // Replaces page labels with page numbers, where `labels` holds the label of each page.
+    // Labels needn't be unique so the first page with a label is used.
+    pub fn resolve_labels(&self, labels: &[Option<String>]) -> Result<Intervals> {
+        let find = |label: &str| {
+            labels
+                .iter()
+                .position(|l| l.as_deref() == Some(label))
+                .map(|pos| pos + 1)
+        };
+        let resolve = |bound: &Bound| match bound {
+            Bound::Page(n) => Ok(*n),
+            Bound::Label(label) => {
+                find(label).ok_or_else(|| anyhow!("no page is labelled {}", label))
+            }
+        };
+
+        let mut items = vec![];
+        for item in self.items.iter() {
+            let iv = match (&item.start, &item.end) {
+                // Labels may contain dashes, so eg. "label:iv-12" is the page labelled "iv-12"
+                // if there is one, or else the pages from "iv" to 12.
+                (Bound::Label(label), Some(Bound::Label(end)))
+                    if label == end && find(label).is_none() =>
+                {
+                    label
+                        .match_indices('-')
+                        .find_map(|(dash, _)| {
+                            let start = find(&label[..dash])?;
+                            let end = label[dash + 1..].parse().ok()?;
+                            Some(Interval {
+                                start,
+                                end: Some(end),
+                            })
+                        })
+                        .ok_or_else(|| anyhow!("no page is labelled {}", label))?
+                }
+                (start, end) => Interval {
+                    start: resolve(start)?,
+                    end: end.as_ref().map(resolve).transpose()?,
+                },
+            };
+            if iv.end.is_some_and(|end| end < iv.start) {
+                return Err(anyhow!(
+                    "invalid interval - the end page comes before the start page"
+                ));
+            }
+            items.push(iv);
+        }
+        Ok(Intervals { items })
+    }
+}
+
+// Ranges starting with a label end with another label, eg. "label:A-1-label:A-9", a page number,
+// eg. "label:iv-12" (see `resolve_labels`), or are left open, eg. "label:iv-".
+impl FromStr for PageSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut items = vec![];
+        for item in s.split(',') {
+            let dash = if item.starts_with("label:") {
+                item.find("-label:")
+                    .or_else(|| item.strip_suffix('-').map(|start| start.len()))
+            } else {
+                item.find('-')
+            };
+            let mut selection = Selection {
+                start: item[..dash.unwrap_or(item.len())].parse()?,
+                end: None,
+            };
+            if let Some(dash) = dash {
+                if item.len() != dash + 1 {
+                    selection.end = Some(item[dash + 1..].parse()?);
+                }
+            } else {
+                selection.end = Some(selection.start.clone());
+            }
+            if let (Bound::Page(start), Some(Bound::Page(end))) = (&selection.start, &selection.end)
+            {
+                if end < start {
+                    return Err(anyhow!(
+                        "invalid interval - end value must be greater than the start value"
+                    ));
+                }
+            }
+            items.push(selection);
+        }
+
+        Ok(Self { items })
+    }
+}
+
 pub struct Size {
     pub width: Option<f32>,
     pub height: Option<f32>,
@@ -120,7 +287,7 @@ impl FromStr for Size {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct Geometry {
     pub width: f64,
     pub height: f64,
@@ -128,6 +295,67 @@ pub struct Geometry {
     pub y: f64,
 }
 
+impl Geometry {
+    pub fn from_bounds(left: f64, bottom: f64, right: f64, top: f64) -> Self {
+        Self {
+            width: right - left,
+            height: top - bottom,
+            x: left,
+            y: bottom,
+        }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn top(&self) -> f64 {
+        self.y + self.height
+    }
+
+    // The smallest geometry containing both.
+    pub fn union(&self, other: &Geometry) -> Self {
+        Self::from_bounds(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.right().max(other.right()),
+            self.top().max(other.top()),
+        )
+    }
+
+    // The smallest geometry containing this one once mapped by the matrix [a, b, c, d, e, f].
+    pub fn transform(&self, m: [f32; 6]) -> Self {
+        let m = m.map(|v| v as f64);
+        let corners = [
+            (self.x, self.y),
+            (self.right(), self.y),
+            (self.x, self.top()),
+            (self.right(), self.top()),
+        ]
+        .map(|(x, y)| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]));
+        let (mut left, mut bottom, mut right, mut top) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for (x, y) in corners {
+            left = left.min(x);
+            bottom = bottom.min(y);
+            right = right.max(x);
+            top = top.max(y);
+        }
+        Self::from_bounds(left, bottom, right, top)
+    }
+}
+
+// Returns a matrix which applies `m` followed by `n`.
+pub fn concat(m: [f32; 6], n: [f32; 6]) -> [f32; 6] {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
 impl FromStr for Geometry {
     type Err = Error;
 
@@ -153,7 +381,7 @@ impl FromStr for Geometry {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct Coords {
     pub x: f64,
     pub y: f64,
@@ -180,3 +408,305 @@ impl FromStr for Coords {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Jpg,
+    Png,
+    Tiff,
+    Pnm,
+    Bmp,
+    Webp,
+    Avif,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpg => "jpg",
+            Self::Png => "png",
+            Self::Tiff => "tiff",
+            Self::Pnm => "pnm",
+            Self::Bmp => "bmp",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+        }
+    }
+
+    pub fn supports_alpha(&self) -> bool {
+        matches!(
+            self,
+            Self::Png | Self::Tiff | Self::Bmp | Self::Webp | Self::Avif
+        )
+    }
+}
+
+// Formats are keyed by their file extension so `--format` accepts eg. `jpeg` or `tif`.
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().trim_start_matches('.') {
+            "jpg" | "jpeg" => Ok(Self::Jpg),
+            "png" => Ok(Self::Png),
+            "tif" | "tiff" => Ok(Self::Tiff),
+            "pnm" | "ppm" | "pgm" | "pbm" => Ok(Self::Pnm),
+            "bmp" => Ok(Self::Bmp),
+            "webp" => Ok(Self::Webp),
+            "avif" => Ok(Self::Avif),
+            _ => Err(anyhow!("unsupported image format")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for TextFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!("invalid text format")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for DataFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(anyhow!("invalid data format")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Color,
+    Gray,
+    Bilevel,
+}
+
+impl FromStr for ColorMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "color" => Ok(Self::Color),
+            "gray" | "grayscale" => Ok(Self::Gray),
+            "bilevel" | "mono" => Ok(Self::Bilevel),
+            _ => Err(anyhow!("invalid color mode")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TiffCompression {
+    None,
+    G4,
+}
+
+impl FromStr for TiffCompression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "g4" | "ccitt" => Ok(Self::G4),
+            _ => Err(anyhow!("invalid TIFF compression")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutAction {
+    Skip,
+    Draft,
+}
+
+impl FromStr for TimeoutAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "draft" => Ok(Self::Draft),
+            _ => Err(anyhow!("invalid timeout action")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileLayout {
+    Dzi,
+    Xyz,
+}
+
+impl FromStr for TileLayout {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "dzi" => Ok(Self::Dzi),
+            "xyz" => Ok(Self::Xyz),
+            _ => Err(anyhow!("invalid tile layout")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Self = Self {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    };
+
+    // PDFium expects colors as 0xAARRGGBB.
+    pub fn argb(&self) -> u32 {
+        (self.a as u32) << 24 | (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return parse_color(s.trim_start_matches('#')).ok_or_else(|| anyhow!("invalid color"));
+
+        fn parse_color(s: &str) -> Option<Color> {
+            if !(s.len() == 6 || s.len() == 8) || !s.is_ascii() {
+                return None;
+            }
+            let channel = |pos: usize| u8::from_str_radix(&s[pos..pos + 2], 16).ok();
+            Some(Color {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+                a: if s.len() == 8 { channel(6)? } else { 255 },
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(ivs: &Intervals, max: usize) -> Vec<usize> {
+        ivs.iter(max).collect()
+    }
+
+    fn labels(labels: &[&str]) -> Vec<Option<String>> {
+        labels
+            .iter()
+            .map(|label| Some(label.to_string()).filter(|label| !label.is_empty()))
+            .collect()
+    }
+
+    #[test]
+    fn colors() {
+        let color = |r, g, b, a| Color { r, g, b, a };
+        assert_eq!("#ff8000".parse::<Color>().unwrap(), color(255, 128, 0, 255));
+        assert_eq!("FF8000".parse::<Color>().unwrap(), color(255, 128, 0, 255));
+        assert_eq!("#00000080".parse::<Color>().unwrap(), color(0, 0, 0, 128));
+        for s in ["", "#", "fff", "#ff00", "#ff80001", "#gg0000", "é1234"] {
+            assert!(s.parse::<Color>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn intervals_round_trip() {
+        for s in ["1", "1-3", "2,4-6,9-", "1-1,3"] {
+            let ivs: Intervals = s.parse().unwrap();
+            let reparsed: Intervals = ivs.to_string().parse().unwrap();
+            assert_eq!(pages(&ivs, 12), pages(&reparsed, 12), "{}", s);
+        }
+        assert_eq!("1-1,3".parse::<Intervals>().unwrap().to_string(), "1,3");
+
+        // Workers are given the pages they should render this way.
+        let ivs: Intervals = vec![1, 2, 3, 7, 9, 10].into_iter().collect();
+        assert_eq!(ivs.to_string(), "1-3,7,9-10");
+        assert_eq!(
+            pages(&ivs.to_string().parse().unwrap(), 10),
+            [1, 2, 3, 7, 9, 10]
+        );
+    }
+
+    #[test]
+    fn intervals_reject_invalid() {
+        assert!("3-1".parse::<Intervals>().is_err());
+        assert!("a".parse::<Intervals>().is_err());
+        assert!("label:iv".parse::<Intervals>().is_err());
+    }
+
+    #[test]
+    fn page_selection_without_labels() {
+        let selection: PageSelection = "2,4-5,9-".parse().unwrap();
+        assert!(!selection.has_labels());
+        let ivs = selection.resolve_labels(&[]).unwrap();
+        assert_eq!(pages(&ivs, 10), [2, 4, 5, 9, 10]);
+    }
+
+    #[test]
+    fn page_selection_with_labels() {
+        let doc = labels(&["", "i", "ii", "iii", "iv", "1", "2", "3", "A-1", "A-2"]);
+        let resolve = |s: &str| {
+            let selection: PageSelection = s.parse().unwrap();
+            assert!(selection.has_labels(), "{}", s);
+            pages(&selection.resolve_labels(&doc).unwrap(), doc.len())
+        };
+        assert_eq!(resolve("label:iv"), [5]);
+        assert_eq!(resolve("label:ii-label:iv"), [3, 4, 5]);
+        assert_eq!(resolve("label:2-"), [7, 8, 9, 10]);
+        assert_eq!(resolve("1,label:3"), [1, 8]);
+        assert_eq!(resolve("3-label:1"), [3, 4, 5, 6]);
+        // Labels containing dashes.
+        assert_eq!(resolve("label:A-1"), [9]);
+        assert_eq!(resolve("label:A-1-label:A-2"), [9, 10]);
+        // A label followed by a page number.
+        assert_eq!(resolve("label:i-4"), [2, 3, 4]);
+    }
+
+    #[test]
+    fn page_selection_uses_first_page_with_label() {
+        let doc = labels(&["1", "2", "1", "2"]);
+        let selection: PageSelection = "label:2".parse().unwrap();
+        assert_eq!(pages(&selection.resolve_labels(&doc).unwrap(), 4), [2]);
+    }
+
+    #[test]
+    fn page_selection_reject_invalid() {
+        let doc = labels(&["i", "ii", "iii", "1"]);
+        let resolve = |s: &str| s.parse::<PageSelection>()?.resolve_labels(&doc);
+        assert!("label:".parse::<PageSelection>().is_err());
+        assert!("label:-label:ii".parse::<PageSelection>().is_err());
+        assert!(resolve("label:v").is_err());
+        assert!(resolve("label:iii-label:i").is_err());
+        assert!(resolve("label:iii-1").is_err());
+        assert!(resolve("label:v-2").is_err());
+    }
+}
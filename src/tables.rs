@@ -0,0 +1,604 @@
+use crate::bindings::{Page, TextPage};
+use crate::syntax::{concat, Geometry};
+use crate::text::{self, Word};
+use pdfium_sys as p;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+// Lines closer than this, in points, are treated as the same line.
+const SNAP: f64 = 2.;
+// Shorter lines are ignored, eg. underlines of single characters.
+const MIN_RULE: f64 = 6.;
+// Filled paths thinner than this are treated as lines.
+const MAX_RULE_WIDTH: f64 = 2.;
+// Gaps between words wider than this many font sizes separate the cells of a borderless table.
+const CELL_GAP: f64 = 1.;
+// Cells of borderless tables are short - longer runs of words are most likely prose in columns.
+const MAX_CELL_WORDS: usize = 5;
+
+#[derive(Serialize)]
+pub struct Table {
+    // In points from the bottom left of the page.
+    pub bounds: Geometry,
+    // Whether the cells were found from the table's ruling lines rather than from the
+    // alignment of its text.
+    pub ruled: bool,
+    pub rows: Vec<Vec<String>>,
+}
+
+// Finds the tables on a page, from top to bottom.
+//
+// Tables with ruling lines on both axes are split into cells along them. The remaining text
+// is searched for borderless tables: runs of lines with wide gaps between their words whose
+// gaps line up into columns.
+pub fn find_tables(page: &Page, text_page: &TextPage) -> Vec<Table> {
+    let lines = text::lines(text_page);
+    let words: Vec<&Word> = lines.iter().flat_map(|line| line.words.iter()).collect();
+    tables_from(&rules(page), words)
+}
+
+fn tables_from(rules: &[Rule], mut words: Vec<&Word>) -> Vec<Table> {
+    let mut tables = vec![];
+    for (rows, cols) in grids(rules) {
+        let bounds =
+            Geometry::from_bounds(cols[0], rows[rows.len() - 1], cols[cols.len() - 1], rows[0]);
+        let (inside, outside): (Vec<_>, Vec<_>) =
+            words.into_iter().partition(|word| contains(&bounds, word));
+        words = outside;
+
+        let mut cells = vec![vec![String::new(); cols.len() - 1]; rows.len() - 1];
+        for word in inside {
+            let (x, y) = center(word);
+            // Rows are ordered from the top down.
+            let row = rows.iter().skip(1).position(|&edge| y >= edge);
+            let col = cols.iter().skip(1).position(|&edge| x <= edge);
+            if let (Some(row), Some(col)) = (row, col) {
+                push_text(&mut cells[row][col], &word.text);
+            }
+        }
+
+        let rows = trim_empty(cells);
+        if !rows.is_empty() {
+            tables.push(Table {
+                bounds,
+                ruled: true,
+                rows,
+            });
+        }
+    }
+
+    tables.extend(borderless_tables(words));
+    tables.sort_by(|a, b| b.bounds.top().total_cmp(&a.bounds.top()));
+    tables
+}
+
+struct Rule {
+    horizontal: bool,
+    // The y of a horizontal line or the x of a vertical one.
+    pos: f64,
+    start: f64,
+    end: f64,
+}
+
+impl Rule {
+    fn crosses(&self, other: &Rule) -> bool {
+        self.horizontal != other.horizontal
+            && other.pos >= self.start - SNAP
+            && other.pos <= self.end + SNAP
+            && self.pos >= other.start - SNAP
+            && self.pos <= other.end + SNAP
+    }
+}
+
+// The horizontal and vertical lines drawn on a page.
+fn rules(page: &Page) -> Vec<Rule> {
+    let mut rules = vec![];
+    // Many generators draw their grids inside form XObjects.
+    for nested in page.nested_objects() {
+        let matrix = nested.matrix;
+        let path = match nested.object.into_path() {
+            Some(path) => path,
+            None => continue,
+        };
+        let (filled, stroked) = path.draw_mode().unwrap_or((false, false));
+
+        if stroked {
+            let m = match path.matrix() {
+                Ok(m) => concat(m, matrix).map(|v| v as f64),
+                Err(_) => continue,
+            };
+            let transform = |x: f32, y: f32| {
+                let (x, y) = (x as f64, y as f64);
+                (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+            };
+
+            let mut current = (0., 0.);
+            let mut subpath_start = (0., 0.);
+            for segment in path.segments() {
+                let point = transform(segment.x, segment.y);
+                if segment.kind as u32 == p::FPDF_SEGMENT_LINETO {
+                    rules.extend(rule(current, point));
+                } else if segment.kind as u32 == p::FPDF_SEGMENT_MOVETO {
+                    subpath_start = point;
+                }
+                if segment.close {
+                    rules.extend(rule(point, subpath_start));
+                }
+                current = point;
+            }
+        } else if filled {
+            // Thin filled rectangles are commonly used instead of stroked lines.
+            if let Ok((left, bottom, right, top)) = path.bounds() {
+                let bounds =
+                    Geometry::from_bounds(left as f64, bottom as f64, right as f64, top as f64)
+                        .transform(matrix);
+                let (left, bottom, right, top) = (bounds.x, bounds.y, bounds.right(), bounds.top());
+                if top - bottom <= MAX_RULE_WIDTH {
+                    let y = (top + bottom) / 2.;
+                    rules.extend(rule((left, y), (right, y)));
+                } else if right - left <= MAX_RULE_WIDTH {
+                    let x = (left + right) / 2.;
+                    rules.extend(rule((x, bottom), (x, top)));
+                }
+            }
+        }
+    }
+    merge_rules(rules)
+}
+
+fn rule(from: (f64, f64), to: (f64, f64)) -> Option<Rule> {
+    let (dx, dy) = ((to.0 - from.0).abs(), (to.1 - from.1).abs());
+    if dy <= SNAP && dx >= MIN_RULE {
+        Some(Rule {
+            horizontal: true,
+            pos: (from.1 + to.1) / 2.,
+            start: from.0.min(to.0),
+            end: from.0.max(to.0),
+        })
+    } else if dx <= SNAP && dy >= MIN_RULE {
+        Some(Rule {
+            horizontal: false,
+            pos: (from.0 + to.0) / 2.,
+            start: from.1.min(to.1),
+            end: from.1.max(to.1),
+        })
+    } else {
+        None
+    }
+}
+
+// Joins collinear lines which overlap or touch, eg. the shared edges of adjacent cells.
+fn merge_rules(mut rules: Vec<Rule>) -> Vec<Rule> {
+    rules.sort_by(|a, b| {
+        (a.horizontal, a.pos)
+            .partial_cmp(&(b.horizontal, b.pos))
+            .unwrap_or(Ordering::Equal)
+    });
+
+    // Lines closer than `SNAP` are collinear, then their overlapping segments are joined.
+    let mut lines: Vec<Vec<Rule>> = vec![];
+    for rule in rules {
+        match lines.last_mut() {
+            Some(line)
+                if line[0].horizontal == rule.horizontal
+                    && (line[line.len() - 1].pos - rule.pos).abs() <= SNAP =>
+            {
+                line.push(rule)
+            }
+            _ => lines.push(vec![rule]),
+        }
+    }
+    let mut merged: Vec<Rule> = vec![];
+    for mut line in lines {
+        line.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let first = merged.len();
+        for rule in line {
+            match merged[first..].last_mut() {
+                Some(last) if rule.start <= last.end + SNAP => last.end = last.end.max(rule.end),
+                _ => merged.push(rule),
+            }
+        }
+    }
+    merged
+}
+
+// Groups crossing lines into grids, returning the y of each row edge from the top down and
+// the x of each column edge from left to right.
+fn grids(rules: &[Rule]) -> Vec<(Vec<f64>, Vec<f64>)> {
+    // Union-find over the lines, joining those which cross.
+    let mut parents: Vec<usize> = (0..rules.len()).collect();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    for i in 0..rules.len() {
+        for j in i + 1..rules.len() {
+            if rules[i].crosses(&rules[j]) {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a] = b;
+            }
+        }
+    }
+
+    let mut grids = vec![];
+    for i in 0..rules.len() {
+        if root(&mut parents, i) != i {
+            continue;
+        }
+        let (mut rows, mut cols) = (vec![], vec![]);
+        for (j, rule) in rules.iter().enumerate() {
+            if root(&mut parents, j) == i {
+                if rule.horizontal {
+                    rows.push(rule.pos);
+                } else {
+                    cols.push(rule.pos);
+                }
+            }
+        }
+        let rows = snap(rows, true);
+        let cols = snap(cols, false);
+        // A single cell is more likely a frame around some text than a table.
+        if rows.len() >= 2 && cols.len() >= 2 && rows.len() + cols.len() > 4 {
+            grids.push((rows, cols));
+        }
+    }
+    grids
+}
+
+// Sorts positions and drops those within `SNAP` of the previous one.
+fn snap(mut positions: Vec<f64>, descending: bool) -> Vec<f64> {
+    positions.sort_by(|a, b| a.total_cmp(b));
+    positions.dedup_by(|a, b| (*a - *b).abs() <= SNAP);
+    if descending {
+        positions.reverse();
+    }
+    positions
+}
+
+struct Row<'a> {
+    bounds: Geometry,
+    // Runs of words separated by gaps too narrow to be between cells.
+    runs: Vec<Vec<&'a Word>>,
+}
+
+impl Row<'_> {
+    fn is_tabular(&self) -> bool {
+        self.runs.len() >= 2
+    }
+}
+
+fn borderless_tables(mut words: Vec<&Word>) -> Vec<Table> {
+    if words.is_empty() {
+        return vec![];
+    }
+
+    // Group the words into rows from the top of the page down.
+    words.sort_by(|a, b| b.bounds.top().total_cmp(&a.bounds.top()));
+    let mut rows: Vec<(Geometry, Vec<&Word>)> = vec![];
+    for word in words {
+        match rows.last_mut() {
+            Some((bounds, row)) if overlaps(bounds, &word.bounds) => {
+                *bounds = bounds.union(&word.bounds);
+                row.push(word);
+            }
+            _ => rows.push((word.bounds, vec![word])),
+        }
+    }
+    let rows: Vec<Row> = rows
+        .into_iter()
+        .map(|(bounds, mut words)| {
+            words.sort_by(|a, b| a.bounds.x.total_cmp(&b.bounds.x));
+            let mut runs: Vec<Vec<&Word>> = vec![];
+            for word in words {
+                match runs.last_mut() {
+                    Some(run) if !is_cell_gap(run[run.len() - 1], word) => run.push(word),
+                    _ => runs.push(vec![word]),
+                }
+            }
+            Row { bounds, runs }
+        })
+        .collect();
+
+    // Tables are runs of tabular rows, allowing single other rows such as headings between
+    // them, without paragraph-sized gaps.
+    let mut tables = vec![];
+    let mut start = 0;
+    while start < rows.len() {
+        if !rows[start].is_tabular() {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 1;
+        while end < rows.len() {
+            let prev = &rows[end - 1];
+            let gap = prev.bounds.y - rows[end].bounds.top();
+            if gap > prev.bounds.height.max(rows[end].bounds.height) * 1.5 {
+                break;
+            }
+            if !rows[end].is_tabular() && !rows.get(end + 1).is_some_and(Row::is_tabular) {
+                break;
+            }
+            end += 1;
+        }
+        tables.extend(borderless_table(&rows[start..end]));
+        start = end;
+    }
+    tables
+}
+
+fn borderless_table(rows: &[Row]) -> Option<Table> {
+    let tabular = rows.iter().filter(|row| row.is_tabular()).count();
+    if rows.len() < 3 || tabular < 2 {
+        return None;
+    }
+
+    let bounds = rows
+        .iter()
+        .skip(1)
+        .fold(rows[0].bounds, |bounds, row| bounds.union(&row.bounds));
+
+    // Columns are separated by gutters which the runs of (nearly) all tabular rows leave
+    // empty, so the odd long label spilling into the next column doesn't merge them.
+    let width = bounds.width.ceil() as usize + 1;
+    let mut coverage = vec![0; width];
+    for row in rows.iter().filter(|row| row.is_tabular()) {
+        for run in row.runs.iter() {
+            let start = (run[0].bounds.x - bounds.x).max(0.) as usize;
+            let end = (run[run.len() - 1].bounds.right() - bounds.x).ceil() as usize;
+            for count in coverage[start..end.min(width)].iter_mut() {
+                *count += 1;
+            }
+        }
+    }
+    let max_overlap = tabular / 10;
+    let mut col_starts = vec![bounds.x];
+    let mut in_gutter = false;
+    for (x, &count) in coverage.iter().enumerate() {
+        if count <= max_overlap {
+            in_gutter = true;
+        } else if in_gutter {
+            col_starts.push(bounds.x + x as f64);
+            in_gutter = false;
+        }
+    }
+    if col_starts.len() < 2 {
+        return None;
+    }
+
+    let mut cells = vec![vec![String::new(); col_starts.len()]; rows.len()];
+    let mut word_counts = vec![];
+    for (row, cells) in rows.iter().zip(cells.iter_mut()) {
+        for run in row.runs.iter() {
+            // The column containing the left edge of the run.
+            let x = run[0].bounds.x + SNAP;
+            let col = col_starts
+                .iter()
+                .rposition(|&start| start <= x)
+                .unwrap_or(0);
+            for word in run {
+                push_text(&mut cells[col], &word.text);
+            }
+            word_counts.push(run.len());
+        }
+    }
+
+    word_counts.sort_unstable();
+    if word_counts[word_counts.len() / 2] > MAX_CELL_WORDS {
+        return None;
+    }
+
+    Some(Table {
+        bounds,
+        ruled: false,
+        rows: trim_empty(cells),
+    })
+}
+
+fn is_cell_gap(prev: &Word, next: &Word) -> bool {
+    let font_size = prev.chars[0].font_size.max(next.chars[0].font_size);
+    next.bounds.x - prev.bounds.right() > font_size * CELL_GAP
+}
+
+fn overlaps(a: &Geometry, b: &Geometry) -> bool {
+    let overlap = a.top().min(b.top()) - a.y.max(b.y);
+    overlap >= a.height.min(b.height) / 2.
+}
+
+fn center(word: &Word) -> (f64, f64) {
+    let bounds = &word.bounds;
+    (bounds.x + bounds.width / 2., bounds.y + bounds.height / 2.)
+}
+
+fn contains(bounds: &Geometry, word: &Word) -> bool {
+    let (x, y) = center(word);
+    x >= bounds.x && x <= bounds.right() && y >= bounds.y && y <= bounds.top()
+}
+
+fn push_text(cell: &mut String, text: &str) {
+    if !cell.is_empty() {
+        cell.push(' ');
+    }
+    cell.push_str(text);
+}
+
+// Drops rows and columns without any text, eg. from double ruling lines.
+fn trim_empty(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let cols = rows.first().map_or(0, Vec::len);
+    let used: Vec<bool> = (0..cols)
+        .map(|col| rows.iter().any(|row| !row[col].is_empty()))
+        .collect();
+    rows.into_iter()
+        .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+        .map(|row| {
+            row.into_iter()
+                .zip(used.iter())
+                .filter(|(_, &used)| used)
+                .map(|(cell, _)| cell)
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::tests::{line, word};
+
+    fn horizontal(y: f64, start: f64, end: f64) -> Rule {
+        Rule {
+            horizontal: true,
+            pos: y,
+            start,
+            end,
+        }
+    }
+
+    fn vertical(x: f64, start: f64, end: f64) -> Rule {
+        Rule {
+            horizontal: false,
+            pos: x,
+            start,
+            end,
+        }
+    }
+
+    // A grid with a row edge at each of `rows` and a column edge at each of `cols`.
+    fn grid(rows: &[f64], cols: &[f64]) -> Vec<Rule> {
+        let (top, bottom) = (rows[0], rows[rows.len() - 1]);
+        let (left, right) = (cols[0], cols[cols.len() - 1]);
+        let mut rules: Vec<Rule> = rows.iter().map(|&y| horizontal(y, left, right)).collect();
+        rules.extend(cols.iter().map(|&x| vertical(x, bottom, top)));
+        rules
+    }
+
+    fn spans(rules: &[Rule]) -> Vec<(bool, f64, f64, f64)> {
+        rules
+            .iter()
+            .map(|rule| (rule.horizontal, rule.pos, rule.start, rule.end))
+            .collect()
+    }
+
+    fn cells(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn rules_from_segments() {
+        assert!(rule((0., 100.), (50., 101.)).is_some_and(|rule| rule.horizontal));
+        assert!(rule((20., 0.), (20., 50.)).is_some_and(|rule| !rule.horizontal));
+        // Too short, and diagonal.
+        assert!(rule((0., 100.), (5., 100.)).is_none());
+        assert!(rule((0., 0.), (50., 50.)).is_none());
+    }
+
+    #[test]
+    fn collinear_rules_are_merged() {
+        let rules = vec![
+            horizontal(100., 50., 100.),
+            horizontal(101., 0., 51.),
+            horizontal(100., 110., 150.),
+            vertical(0., 0., 100.),
+            horizontal(50., 0., 100.),
+        ];
+        assert_eq!(
+            spans(&merge_rules(rules)),
+            [
+                (false, 0., 0., 100.),
+                (true, 50., 0., 100.),
+                (true, 101., 0., 100.),
+                (true, 100., 110., 150.),
+            ]
+        );
+    }
+
+    #[test]
+    fn grids_from_crossing_rules() {
+        let mut rules = grid(&[100., 80., 60.], &[0., 100., 200.]);
+        // A frame around a single cell, away from the table.
+        rules.extend(grid(&[300., 250.], &[0., 200.]));
+        // Lines within `SNAP` of each other are the same edge.
+        rules.push(horizontal(81., 0., 200.));
+        assert_eq!(
+            grids(&rules),
+            [(vec![100., 80., 60.], vec![0., 100., 200.])]
+        );
+        assert!(grids(&[]).is_empty());
+    }
+
+    #[test]
+    fn ruled_table() {
+        let rules = grid(&[100., 85., 70.], &[0., 100., 200., 203.]);
+        let words = [
+            word("Name", 10., 88.),
+            word("Age", 110., 88.),
+            word("Ada", 10., 73.),
+            word("Love", 35., 73.),
+            word("36", 110., 73.),
+            word("Below", 10., 40.),
+        ];
+        let tables = tables_from(&rules, words.iter().collect());
+        assert_eq!(tables.len(), 1);
+        assert!(tables[0].ruled);
+        let bounds = &tables[0].bounds;
+        assert_eq!(
+            (bounds.x, bounds.y, bounds.right(), bounds.top()),
+            (0., 70., 203., 100.)
+        );
+        // The empty column between the doubled rules at the right is dropped.
+        assert_eq!(
+            tables[0].rows,
+            cells(&[&["Name", "Age"], &["Ada Love", "36"]])
+        );
+    }
+
+    #[test]
+    fn borderless_table() {
+        let lines = [
+            line("Prices", 0., 130.),
+            line("Item", 0., 100.),
+            line("Qty", 100., 100.),
+            line("Price each", 200., 100.),
+            line("Apple", 0., 88.),
+            line("3", 100., 88.),
+            line("0.50", 200., 88.),
+            line("Pear", 0., 76.),
+            line("12", 100., 76.),
+            line("1.20", 200., 76.),
+        ];
+        let words = lines.iter().flat_map(|line| line.words.iter()).collect();
+        let tables = tables_from(&[], words);
+        assert_eq!(tables.len(), 1);
+        assert!(!tables[0].ruled);
+        assert_eq!(
+            tables[0].rows,
+            cells(&[
+                &["Item", "Qty", "Price each"],
+                &["Apple", "3", "0.50"],
+                &["Pear", "12", "1.20"],
+            ])
+        );
+    }
+
+    #[test]
+    fn prose_is_not_a_table() {
+        let lines = [
+            line("Some words making up a paragraph", 0., 100.),
+            line("which wraps over several lines", 0., 88.),
+            line("of the page", 0., 76.),
+        ];
+        let words = lines.iter().flat_map(|line| line.words.iter()).collect();
+        assert!(tables_from(&[], words).is_empty());
+    }
+
+    #[test]
+    fn empty_rows_and_columns_are_trimmed() {
+        let rows = cells(&[&["", "a", "", "b"], &["", "", "", ""], &["", "c", "", ""]]);
+        assert_eq!(trim_empty(rows), cells(&[&["a", "b"], &["c", ""]]));
+        assert!(trim_empty(vec![]).is_empty());
+    }
+}
@@ -0,0 +1,615 @@
+use crate::bindings::{TextChar, TextPage};
+use crate::syntax::{Coords, Geometry};
+use serde::Serialize;
+
+// Characters further apart than this fraction of their font size start a new word.
+const WORD_GAP: f64 = 0.25;
+
+#[derive(Serialize)]
+pub struct Line {
+    pub text: String,
+    pub bounds: Geometry,
+    pub words: Vec<Word>,
+}
+
+#[derive(Serialize)]
+pub struct Word {
+    pub text: String,
+    pub bounds: Geometry,
+    pub chars: Vec<Char>,
+}
+
+#[derive(Serialize)]
+pub struct Char {
+    pub text: char,
+    pub bounds: Geometry,
+    // The start of the character's baseline.
+    pub origin: Coords,
+    pub font_size: f64,
+    pub font_name: String,
+    pub font_flags: i32,
+    pub font_weight: i32,
+    pub fill_color: [u8; 4],
+    pub angle: f32,
+}
+
+impl From<TextChar> for Char {
+    fn from(c: TextChar) -> Self {
+        Self {
+            text: c.text,
+            bounds: Geometry::from_bounds(c.left, c.bottom, c.right, c.top),
+            origin: Coords {
+                x: c.origin_x,
+                y: c.origin_y,
+            },
+            font_size: c.font_size,
+            font_name: c.font_name,
+            font_flags: c.font_flags,
+            font_weight: c.font_weight,
+            fill_color: c.fill_color,
+            angle: c.angle,
+        }
+    }
+}
+
+impl Line {
+    fn new(word: Word) -> Self {
+        Self {
+            text: word.text.clone(),
+            bounds: word.bounds,
+            words: vec![word],
+        }
+    }
+
+    fn push(&mut self, word: Word) {
+        self.text.push(' ');
+        self.text.push_str(&word.text);
+        self.bounds = self.bounds.union(&word.bounds);
+        self.words.push(word);
+    }
+}
+
+impl Word {
+    fn new(c: Char) -> Self {
+        Self {
+            text: c.text.to_string(),
+            bounds: c.bounds,
+            chars: vec![c],
+        }
+    }
+
+    fn push(&mut self, c: Char) {
+        self.text.push(c.text);
+        self.bounds = self.bounds.union(&c.bounds);
+        self.chars.push(c);
+    }
+
+    fn gap(&self, c: &Char) -> f64 {
+        c.bounds.x - self.bounds.right()
+    }
+}
+
+// Groups a page's characters into words and lines, in PDFium's text order.
+pub fn lines(text_page: &TextPage) -> Vec<Line> {
+    let mut lines: Vec<Line> = vec![];
+    let mut line: Option<Line> = None;
+    let mut word: Option<Word> = None;
+
+    for c in text_page.chars() {
+        // PDFium generates spaces and line breaks where it infers them from the layout.
+        let is_break = c.text == '\r' || c.text == '\n';
+        if is_break || c.generated || c.text.is_whitespace() || c.text == '\0' {
+            if let Some(word) = word.take() {
+                push_word(&mut line, word);
+            }
+            if is_break {
+                lines.extend(line.take());
+            }
+            continue;
+        }
+
+        let c = Char::from(c);
+        if breaks_line(line.as_ref(), word.as_ref(), &c) {
+            if let Some(word) = word.take() {
+                push_word(&mut line, word);
+            }
+            lines.extend(line.take());
+        }
+        match word.as_mut() {
+            Some(current) if current.gap(&c) <= c.font_size * WORD_GAP => current.push(c),
+            _ => {
+                if let Some(word) = word.take() {
+                    push_word(&mut line, word);
+                }
+                word = Some(Word::new(c));
+            }
+        }
+    }
+
+    if let Some(word) = word.take() {
+        push_word(&mut line, word);
+    }
+    lines.extend(line.take());
+    lines
+}
+
+// Whether `c` starts a new line - ie. it overlaps vertically with the current line by less
+// than half its height, or jumps back towards the start of the line.
+fn breaks_line(line: Option<&Line>, word: Option<&Word>, c: &Char) -> bool {
+    let bounds = match (line, word) {
+        (Some(line), Some(word)) => line.bounds.union(&word.bounds),
+        (Some(line), None) => line.bounds,
+        (None, Some(word)) => word.bounds,
+        (None, None) => return false,
+    };
+    let last = word
+        .and_then(|word| word.chars.last())
+        .or_else(|| line.and_then(|line| line.words.last()?.chars.last()));
+
+    let overlap = bounds.top().min(c.bounds.top()) - bounds.y.max(c.bounds.y);
+    let height = bounds.height.min(c.bounds.height);
+    overlap < height / 2. || last.is_some_and(|last| c.bounds.x < last.bounds.x - c.font_size)
+}
+
+fn push_word(line: &mut Option<Line>, word: Word) {
+    match line.as_mut() {
+        Some(line) => line.push(word),
+        None => *line = Some(Line::new(word)),
+    }
+}
+
+// Gaps between words wider than this many font sizes are treated as column gutters.
+pub const COLUMN_GAP: f64 = 1.5;
+
+// A run of words read as one line of a column, see `blocks`.
+pub struct Block<'a> {
+    // Left edge of the enclosing column.
+    pub origin: f64,
+    pub bounds: Geometry,
+    pub words: Vec<&'a Word>,
+}
+
+// Splits words into columns and lines, returning the lines in reading order.
+//
+// Words are split by recursive XY-cuts: a region is split at its widest vertical gutter if
+// there's one, otherwise at its widest horizontal gap, so a full-width heading is cut off
+// before the columns beneath it.
+pub fn blocks<'a>(words: Vec<&'a Word>) -> Vec<Block<'a>> {
+    if words.is_empty() {
+        return vec![];
+    }
+    let font_size = median(words.iter().map(|word| word.chars[0].font_size));
+    let items = words.into_iter().map(|word| (word.bounds, word)).collect();
+
+    let mut groups = vec![];
+    xy_cut(items, font_size * COLUMN_GAP, &mut groups);
+    groups
+        .into_iter()
+        .map(|(origin, mut items)| {
+            items.sort_by(|a, b| a.0.x.total_cmp(&b.0.x));
+            let bounds = items
+                .iter()
+                .skip(1)
+                .fold(items[0].0, |bounds, item| bounds.union(&item.0));
+            Block {
+                origin,
+                bounds,
+                words: items.into_iter().map(|(_, word)| word).collect(),
+            }
+        })
+        .collect()
+}
+
+// Orders boxes, eg. the regions of a page, for reading in the same way as `blocks`. Boxes
+// which can't be cut apart are read top to bottom then left to right.
+pub fn reading_order(bounds: &[Geometry], column_gap: f64) -> Vec<usize> {
+    if bounds.is_empty() {
+        return vec![];
+    }
+    let items = bounds.iter().copied().zip(0..).collect();
+
+    let mut groups = vec![];
+    xy_cut(items, column_gap, &mut groups);
+    groups
+        .into_iter()
+        .flat_map(|(_, mut items)| {
+            items.sort_by(|a, b| {
+                b.0.top()
+                    .total_cmp(&a.0.top())
+                    .then(a.0.x.total_cmp(&b.0.x))
+            });
+            items.into_iter().map(|(_, i)| i)
+        })
+        .collect()
+}
+
+// Renders a page's text in reading order, roughly preserving its spacing like
+// `pdftotext -layout`.
+pub fn layout(text_page: &TextPage, dehyphenate: bool) -> String {
+    layout_lines(&lines(text_page), dehyphenate)
+}
+
+fn layout_lines(lines: &[Line], dehyphenate: bool) -> String {
+    let words: Vec<&Word> = lines.iter().flat_map(|line| line.words.iter()).collect();
+    if words.is_empty() {
+        return String::new();
+    }
+
+    let font_size = median(words.iter().map(|word| word.chars[0].font_size));
+    let char_width = words.iter().map(|word| word.bounds.width).sum::<f64>()
+        / words.iter().map(|word| word.chars.len()).sum::<usize>() as f64;
+
+    // Blocks which sit side by side (eg. cells of a table row) are written on the same line.
+    let mut rows: Vec<Vec<Block>> = vec![];
+    for block in blocks(words) {
+        match rows.last_mut().and_then(|row| row.last()) {
+            Some(last) if same_row(last, &block) => rows.last_mut().unwrap().push(block),
+            _ => rows.push(vec![block]),
+        }
+    }
+
+    let mut out: Vec<Option<String>> = vec![];
+    let mut prev: Option<Geometry> = None;
+    for row in rows.iter() {
+        let bounds = row
+            .iter()
+            .skip(1)
+            .fold(row[0].bounds, |bounds, block| bounds.union(&block.bounds));
+        // A paragraph break, or the start of a new column.
+        if let Some(prev) = prev {
+            if prev.y - bounds.top() > font_size || bounds.top() > prev.top() {
+                out.push(None);
+            }
+        }
+        prev = Some(bounds);
+
+        let mut line = String::new();
+        for word in row.iter().flat_map(|block| block.words.iter()) {
+            let col = ((word.bounds.x - row[0].origin) / char_width)
+                .round()
+                .max(0.) as usize;
+            let len = line.chars().count();
+            let spaces = if len == 0 {
+                col
+            } else {
+                col.saturating_sub(len).max(1)
+            };
+            line.push_str(&" ".repeat(spaces));
+            line.push_str(&word.text);
+        }
+        out.push(Some(line));
+    }
+
+    if dehyphenate {
+        join_hyphenated(&mut out);
+    }
+
+    let mut text = String::new();
+    for line in out.iter() {
+        match line.as_deref().map(str::trim_end) {
+            // All of the line was joined onto the previous one.
+            Some("") => continue,
+            Some(line) => text.push_str(line),
+            None => {}
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Splits items into groups which can't be cut apart, in reading order, along with the left
+// edge of each group's column.
+fn xy_cut<T>(
+    items: Vec<(Geometry, T)>,
+    column_gap: f64,
+    groups: &mut Vec<(f64, Vec<(Geometry, T)>)>,
+) {
+    let origin = items.iter().map(|item| item.0.x).fold(f64::MAX, f64::min);
+    cut(items, origin, column_gap, groups);
+
+    fn cut<T>(
+        items: Vec<(Geometry, T)>,
+        origin: f64,
+        column_gap: f64,
+        groups: &mut Vec<(f64, Vec<(Geometry, T)>)>,
+    ) {
+        let columns = widest_gap(items.iter().map(|item| (item.0.x, item.0.right())));
+        if let Some((gap, at)) = columns {
+            if gap >= column_gap {
+                let (left, right): (Vec<_>, Vec<_>) =
+                    items.into_iter().partition(|item| item.0.x < at);
+                let right_origin = right.iter().map(|item| item.0.x).fold(f64::MAX, f64::min);
+                cut(left, origin, column_gap, groups);
+                cut(right, right_origin, column_gap, groups);
+                return;
+            }
+        }
+
+        let rows = widest_gap(items.iter().map(|item| (item.0.y, item.0.top())));
+        if let Some((_, at)) = rows {
+            let (below, above): (Vec<_>, Vec<_>) =
+                items.into_iter().partition(|item| item.0.y < at);
+            cut(above, origin, column_gap, groups);
+            cut(below, origin, column_gap, groups);
+            return;
+        }
+
+        groups.push((origin, items));
+    }
+}
+
+// The widest gap between a set of intervals as (width, midpoint), if they don't all overlap.
+fn widest_gap(intervals: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let mut intervals: Vec<_> = intervals.collect();
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut widest: Option<(f64, f64)> = None;
+    let mut end = f64::MIN;
+    for (i, (start, stop)) in intervals.into_iter().enumerate() {
+        let gap = start - end;
+        if i > 0 && gap > 0. && !widest.is_some_and(|(widest, _)| gap <= widest) {
+            widest = Some((gap, end + gap / 2.));
+        }
+        end = end.max(stop);
+    }
+    widest
+}
+
+fn same_row(a: &Block, b: &Block) -> bool {
+    let overlap = a.bounds.top().min(b.bounds.top()) - a.bounds.y.max(b.bounds.y);
+    b.bounds.x >= a.bounds.right() && overlap >= a.bounds.height.min(b.bounds.height) / 2.
+}
+
+// Joins words broken across lines, eg. "hyphen-" followed by "ated text" becomes "hyphenated"
+// followed by "text". Only lines within the same paragraph are joined.
+fn join_hyphenated(lines: &mut [Option<String>]) {
+    for i in 1..lines.len() {
+        let (head, tail) = lines.split_at_mut(i);
+        let (prev, next) = match (head[i - 1].as_mut(), tail[0].as_mut()) {
+            (Some(prev), Some(next)) => (prev, next),
+            _ => continue,
+        };
+
+        let trimmed = prev.trim_end();
+        let mut chars = trimmed.chars().rev();
+        let is_hyphen = matches!(chars.next(), Some('-' | '\u{ad}' | '\u{2}'));
+        let after_letter = chars.next().is_some_and(char::is_alphabetic);
+        let rest = next.trim_start();
+        if !(is_hyphen && after_letter && rest.starts_with(char::is_lowercase)) {
+            continue;
+        }
+
+        let word_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, rest) = rest.split_at(word_len);
+        let mut joined =
+            trimmed[..trimmed.len() - trimmed.chars().last().unwrap().len_utf8()].to_owned();
+        joined.push_str(word);
+        // Keep the rest of the next line at its original indentation.
+        let indent = next.len() - next.trim_start().len();
+        let rest = format!("{}{}", " ".repeat(indent), rest.trim_start());
+
+        *prev = joined;
+        *next = rest;
+    }
+}
+
+pub fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<_> = values.collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
+
+// Returns a font's name without the tag of six capital letters which subset fonts are given,
+// eg. "Helvetica" for "ABCDEF+Helvetica", or None if the font isn't a subset.
+pub fn strip_subset_tag(font_name: &str) -> Option<&str> {
+    let (tag, name) = font_name.split_once('+')?;
+    if tag.len() == 6 && tag.chars().all(|c| c.is_ascii_uppercase()) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Words are 10pt with each character 5pt wide, `x` and `y` being their bottom left.
+    pub fn word(text: &str, x: f64, y: f64) -> Word {
+        let chars: Vec<Char> = text
+            .chars()
+            .enumerate()
+            .map(|(pos, c)| {
+                let left = x + pos as f64 * 5.;
+                Char {
+                    text: c,
+                    bounds: Geometry::from_bounds(left, y, left + 5., y + 10.),
+                    origin: Coords { x: left, y },
+                    font_size: 10.,
+                    font_name: "Helvetica".to_string(),
+                    font_flags: 0,
+                    font_weight: 400,
+                    fill_color: [0, 0, 0, 255],
+                    angle: 0.,
+                }
+            })
+            .collect();
+        Word {
+            text: text.to_string(),
+            bounds: Geometry::from_bounds(x, y, x + text.len() as f64 * 5., y + 10.),
+            chars,
+        }
+    }
+
+    // Lays out words left to right from `x`, a space apart.
+    pub fn line(text: &str, x: f64, y: f64) -> Line {
+        let mut words = text.split(' ').scan(x, |x, text| {
+            let word = word(text, *x, y);
+            *x = word.bounds.right() + 5.;
+            Some(word)
+        });
+        let mut line = Line::new(words.next().unwrap());
+        words.for_each(|word| line.push(word));
+        line
+    }
+
+    fn block_text(block: &Block) -> String {
+        let words: Vec<&str> = block.words.iter().map(|word| word.text.as_str()).collect();
+        words.join(" ")
+    }
+
+    fn join(lines: &[Option<&str>]) -> Vec<Option<String>> {
+        let mut lines: Vec<Option<String>> =
+            lines.iter().map(|line| line.map(str::to_string)).collect();
+        join_hyphenated(&mut lines);
+        lines
+    }
+
+    #[test]
+    fn widest_gaps() {
+        let gap = |intervals: &[(f64, f64)]| widest_gap(intervals.iter().copied());
+        assert_eq!(gap(&[(30., 40.), (0., 10.), (12., 20.)]), Some((10., 25.)));
+        assert_eq!(gap(&[(0., 10.), (14., 20.), (22., 30.)]), Some((4., 12.)));
+        // The first interval covers the others.
+        assert_eq!(gap(&[(0., 50.), (10., 20.), (30., 40.)]), None);
+        assert_eq!(gap(&[(0., 10.), (10., 20.)]), None);
+        assert_eq!(gap(&[(0., 10.)]), None);
+        assert_eq!(gap(&[]), None);
+    }
+
+    #[test]
+    fn two_column_reading_order() {
+        let lines = [
+            line("A heading across both columns of the page", 0., 130.),
+            line("left one", 0., 100.),
+            line("right one", 200., 100.),
+            line("left two", 0., 88.),
+            line("right two", 200., 88.),
+            line("left three", 0., 76.),
+        ];
+        let words = lines.iter().flat_map(|line| line.words.iter()).collect();
+        let blocks = blocks(words);
+        let texts: Vec<String> = blocks.iter().map(block_text).collect();
+        assert_eq!(
+            texts,
+            [
+                "A heading across both columns of the page",
+                "left one",
+                "left two",
+                "left three",
+                "right one",
+                "right two",
+            ]
+        );
+        let origins: Vec<f64> = blocks.iter().map(|block| block.origin).collect();
+        assert_eq!(origins, [0., 0., 0., 0., 200., 200.]);
+    }
+
+    #[test]
+    fn column_gap_threshold() {
+        // Gutters are at least 1.5 font sizes, ie. 15pt here.
+        for (gap, columns) in [(14., 1), (15., 2), (30., 2)] {
+            let words = [word("left", 0., 100.), word("right", 20. + gap, 100.)];
+            assert_eq!(blocks(words.iter().collect()).len(), columns, "{}", gap);
+        }
+    }
+
+    #[test]
+    fn layout_two_columns() {
+        let lines = [
+            line("left one", 0., 100.),
+            line("right one", 200., 100.),
+            line("left two", 0., 88.),
+            line("right two", 200., 88.),
+        ];
+        assert_eq!(
+            layout_lines(&lines, false),
+            "left one\nleft two\n\nright one\nright two\n"
+        );
+    }
+
+    #[test]
+    fn layout_keeps_spacing_within_a_row() {
+        // The blocks sit side by side so they share a line, spaced by the average character
+        // width.
+        let lines = [line("Name", 0., 100.), line("Value", 100., 100.)];
+        assert_eq!(
+            layout_lines(&lines, false),
+            format!("Name{}Value\n", " ".repeat(16))
+        );
+        assert_eq!(layout_lines(&[], false), "");
+    }
+
+    #[test]
+    fn layout_paragraph_breaks() {
+        let lines = [
+            line("first paragraph", 0., 100.),
+            line("second para-", 0., 70.),
+            line("graph", 0., 58.),
+        ];
+        assert_eq!(
+            layout_lines(&lines, false),
+            "first paragraph\n\nsecond para-\ngraph\n"
+        );
+        assert_eq!(
+            layout_lines(&lines, true),
+            "first paragraph\n\nsecond paragraph\n"
+        );
+    }
+
+    #[test]
+    fn hyphens_are_joined() {
+        assert_eq!(
+            join(&[Some("a hyphen-"), Some("ated text")]),
+            [Some("a hyphenated".to_string()), Some("text".to_string())]
+        );
+        // The rest of the line keeps its indentation.
+        assert_eq!(
+            join(&[Some("a hyphen-"), Some("    ated text")]),
+            [
+                Some("a hyphenated".to_string()),
+                Some("    text".to_string())
+            ]
+        );
+        // Soft hyphens, including the '\u{2}' PDFium reports for them.
+        assert_eq!(
+            join(&[Some("co\u{ad}"), Some("operate now")]),
+            [Some("cooperate".to_string()), Some("now".to_string())]
+        );
+        assert_eq!(
+            join(&[Some("co\u{2}  "), Some("operate")]),
+            [Some("cooperate".to_string()), Some(String::new())]
+        );
+    }
+
+    #[test]
+    fn hyphens_are_kept() {
+        for lines in [
+            [Some("Smith-"), Some("Jones")],
+            [Some("pages 1-"), Some("to 9")],
+            [Some("a -"), Some("dash")],
+            [Some("a hyphen-"), None],
+        ] {
+            assert_eq!(
+                join(&lines),
+                lines.map(|line| line.map(str::to_string)),
+                "{:?}",
+                lines
+            );
+        }
+    }
+
+    #[test]
+    fn subset_tags() {
+        assert_eq!(strip_subset_tag("ABCDEF+Helvetica"), Some("Helvetica"));
+        assert_eq!(
+            strip_subset_tag("QWERTY+Times-Bold+X"),
+            Some("Times-Bold+X")
+        );
+        assert_eq!(strip_subset_tag("Helvetica"), None);
+        assert_eq!(strip_subset_tag("ABCDE+Helvetica"), None);
+        assert_eq!(strip_subset_tag("abcdef+Helvetica"), None);
+        assert_eq!(strip_subset_tag("ABC1EF+Helvetica"), None);
+        assert_eq!(strip_subset_tag("Helvetica+Bold"), None);
+    }
+}